@@ -31,9 +31,9 @@ struct Opt {
     #[structopt(short, long, default_value = "northstar.toml")]
     pub config: PathBuf,
 
-    /// Do not enter a mount namespace if this option is set Be aware that in
-    /// case of a non normal termination of the runtime the images mounted in
-    /// `run_dir` have to be umounted manually before starting the runtime again.
+    /// Do not enter a mount namespace if this option is set. Images left mounted
+    /// in `run_dir` after a non normal termination of the runtime are umounted
+    /// when the runtime is started again.
     #[structopt(short, long)]
     pub disable_mount_namespace: bool,
 }
@@ -81,7 +81,11 @@ fn generate_seccomp() {
             .allowlist_type("seccomp_data")
             .allowlist_type("sock_fprog")
             .allowlist_var("BPF_ABS")
+            .allowlist_var("BPF_JA")
             .allowlist_var("BPF_JEQ")
+            .allowlist_var("BPF_JGE")
+            .allowlist_var("BPF_JGT")
+            .allowlist_var("BPF_JSET")
             .allowlist_var("BPF_JMP")
             .allowlist_var("BPF_K")
             .allowlist_var("BPF_LD")
@@ -89,8 +93,13 @@ fn generate_seccomp() {
             .allowlist_var("BPF_W")
             .allowlist_var("BPF_MAXINSNS")
             .allowlist_var("SECCOMP_RET_ALLOW")
+            .allowlist_var("SECCOMP_RET_DATA")
+            .allowlist_var("SECCOMP_RET_ERRNO")
             .allowlist_var("SECCOMP_RET_LOG")
             .allowlist_var("SECCOMP_RET_KILL")
+            .allowlist_var("SECCOMP_RET_KILL_PROCESS")
+            .allowlist_var("SECCOMP_RET_KILL_THREAD")
+            .allowlist_var("SECCOMP_RET_TRAP")
             .allowlist_var("AUDIT_ARCH_X86_64")
             .allowlist_var("AUDIT_ARCH_AARCH64")
             .generate()
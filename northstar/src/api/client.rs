@@ -15,15 +15,15 @@
 use super::{
     codec::{framed, Framed},
     model::{
-        self, Connect, Container, ContainerData, Message, MountResult, Notification, Payload,
-        RepositoryId, Request, Response,
+        self, Connect, Container, ContainerData, ExecOutput, Message, MessageId, MountResult,
+        Notification, Payload, RepositoryId, Request, Response, Signal,
     },
 };
 use futures::{SinkExt, Stream, StreamExt};
 use log::{debug, info};
 use npk::manifest::Version;
 use std::{
-    collections::HashSet,
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
     pin::Pin,
     task::Poll,
@@ -85,6 +85,8 @@ pub struct Client {
 enum ClientRequest {
     Request(Request),
     Install(PathBuf, String),
+    Update(PathBuf, Container),
+    Exec(Request, mpsc::Sender<ExecOutput>),
 }
 
 impl<'a> Client {
@@ -160,6 +162,9 @@ impl<'a> Client {
         let (request_tx, mut request_rx) =
             mpsc::channel::<(ClientRequest, oneshot::Sender<Result<Response, Error>>)>(10);
         let mut response_tx = Option::<oneshot::Sender<Result<Response, Error>>>::None;
+        // Output receivers of executed commands by request id and the id of a pending exec
+        let mut exec_tx = HashMap::<MessageId, mpsc::Sender<ExecOutput>>::new();
+        let mut exec_pending = Option::<MessageId>::None;
 
         let mut connection = time::timeout(timeout, Self::connect(url, notifications, timeout))
             .await
@@ -176,6 +181,10 @@ impl<'a> Client {
                                 Payload::Connect(_) => break Err(Error::Protocol),
                                 Payload::Request(_) => break Err(Error::Protocol),
                                 Payload::Response(r) => {
+                                    // Forget the output sender of a failed exec
+                                    if let (Some(id), Response::Err(_)) = (exec_pending.take(), &r) {
+                                        exec_tx.remove(&id);
+                                    }
                                     if let Some(r_tx) = response_tx.take() {
                                         r_tx.send(Ok(r)).ok();
                                     } else {
@@ -185,6 +194,14 @@ impl<'a> Client {
                                 Payload::Notification(n) => if notification_tx.send(Ok(n)).await.is_err() {
                                     break Ok(());
                                 }
+                                Payload::Exec(output) => {
+                                    let exit = matches!(output, ExecOutput::Exit(_));
+                                    if let Some(tx) = exec_tx.get(&message.id) {
+                                        if tx.send(output).await.is_err() || exit {
+                                            exec_tx.remove(&message.id);
+                                        }
+                                    }
+                                }
                             },
                             Some(Err(e)) => break Err(Error::Io(e)),
                             None => {
@@ -206,14 +223,50 @@ impl<'a> Client {
                                         }
                                     }
                                     ClientRequest::Install(npk, repository) => {
-                                        let mut file = fs::File::open(npk).await.expect("Failed to open"); // TODO
-                                        let size = file.metadata().await.unwrap().len();
+                                        let (mut file, size) = match open(&npk).await {
+                                            Ok(npk) => npk,
+                                            Err(e) => {
+                                                drop(r_tx.send(Err(Error::Io(e))));
+                                                continue;
+                                            }
+                                        };
                                         let request = Request::Install(repository, size);
                                         match connection.send(Message::new_request(request)).await {
-                                            Ok(_) => response_tx = Some(r_tx), // Store the reponse tx part
+                                            Ok(_) => {
+                                                response_tx = Some(r_tx); // Store the reponse tx part
+                                                io::copy(&mut file, &mut connection).await?;
+                                            }
+                                            Err(e) => drop(r_tx.send(Err(Error::Io(e)))),
+                                        }
+                                    }
+                                    ClientRequest::Update(npk, container) => {
+                                        let (mut file, size) = match open(&npk).await {
+                                            Ok(npk) => npk,
+                                            Err(e) => {
+                                                drop(r_tx.send(Err(Error::Io(e))));
+                                                continue;
+                                            }
+                                        };
+                                        let request = Request::Update(container, size);
+                                        match connection.send(Message::new_request(request)).await {
+                                            Ok(_) => {
+                                                response_tx = Some(r_tx); // Store the reponse tx part
+                                                io::copy(&mut file, &mut connection).await?;
+                                            }
+                                            Err(e) => drop(r_tx.send(Err(Error::Io(e)))),
+                                        }
+                                    }
+                                    ClientRequest::Exec(request, output_tx) => {
+                                        let message = Message::new_request(request);
+                                        let id = message.id.clone();
+                                        match connection.send(message).await {
+                                            Ok(_) => {
+                                                response_tx = Some(r_tx);
+                                                exec_tx.insert(id.clone(), output_tx);
+                                                exec_pending = Some(id);
+                                            }
                                             Err(e) => drop(r_tx.send(Err(Error::Io(e)))),
                                         }
-                                        io::copy(&mut file, &mut connection).await?;
                                     }
                                 }
                             }
@@ -329,7 +382,7 @@ impl<'a> Client {
         }
     }
 
-    /// Stop container with name
+    /// Stop container with name. The manifest stop timeout is used if `timeout` is `None`.
     ///
     /// ```no_run
     /// # use futures::StreamExt;
@@ -340,7 +393,7 @@ impl<'a> Client {
     /// # #[tokio::main]
     /// # async fn main() {
     /// #   let mut client = Client::new(&url::Url::parse("tcp://localhost:4200").unwrap(), None, Duration::from_secs(10)).await.unwrap();
-    /// client.stop("hello", &Version::parse("0.0.1").unwrap(), Duration::from_secs(3)).await.expect("Failed to start \"hello\"");
+    /// client.stop("hello", &Version::parse("0.0.1").unwrap(), Some(Duration::from_secs(3))).await.expect("Failed to start \"hello\"");
     /// // Print stop notification
     /// println!("{:#?}", client.next().await);
     /// # }
@@ -349,12 +402,12 @@ impl<'a> Client {
         &self,
         name: &str,
         version: &Version,
-        timeout: time::Duration,
+        timeout: Option<time::Duration>,
     ) -> Result<(), Error> {
         match self
             .request(Request::Stop(
                 Container::new(name.to_string(), version.clone()),
-                timeout.as_secs(),
+                timeout.map(|timeout| timeout.as_secs()),
             ))
             .await?
         {
@@ -364,6 +417,131 @@ impl<'a> Client {
         }
     }
 
+    /// Pause container with name. All processes of the container are frozen.
+    ///
+    /// ```no_run
+    /// # use tokio::time::Duration;
+    /// # use northstar::api::client::Client;
+    /// # use npk::manifest::Version;
+    /// #
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let mut client = Client::new(&url::Url::parse("tcp://localhost:4200").unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// client.pause("hello", &Version::parse("0.0.1").unwrap()).await.expect("Failed to pause \"hello\"");
+    /// # }
+    /// ```
+    pub async fn pause(&self, name: &str, version: &Version) -> Result<(), Error> {
+        match self
+            .request(Request::Pause(Container::new(
+                name.to_string(),
+                version.clone(),
+            )))
+            .await?
+        {
+            Response::Ok(()) => Ok(()),
+            Response::Err(e) => Err(Error::Api(e)),
+            _ => Err(Error::Protocol),
+        }
+    }
+
+    /// Resume a paused container with name
+    ///
+    /// ```no_run
+    /// # use tokio::time::Duration;
+    /// # use northstar::api::client::Client;
+    /// # use npk::manifest::Version;
+    /// #
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let mut client = Client::new(&url::Url::parse("tcp://localhost:4200").unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// client.resume("hello", &Version::parse("0.0.1").unwrap()).await.expect("Failed to resume \"hello\"");
+    /// # }
+    /// ```
+    pub async fn resume(&self, name: &str, version: &Version) -> Result<(), Error> {
+        match self
+            .request(Request::Resume(Container::new(
+                name.to_string(),
+                version.clone(),
+            )))
+            .await?
+        {
+            Response::Ok(()) => Ok(()),
+            Response::Err(e) => Err(Error::Api(e)),
+            _ => Err(Error::Protocol),
+        }
+    }
+
+    /// Send a signal to the processes of container with name
+    ///
+    /// ```no_run
+    /// # use tokio::time::Duration;
+    /// # use northstar::api::client::Client;
+    /// # use npk::manifest::Version;
+    /// #
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let mut client = Client::new(&url::Url::parse("tcp://localhost:4200").unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// // Send SIGHUP
+    /// client.kill("hello", &Version::parse("0.0.1").unwrap(), 1).await.expect("Failed to signal \"hello\"");
+    /// # }
+    /// ```
+    pub async fn kill(&self, name: &str, version: &Version, signal: Signal) -> Result<(), Error> {
+        match self
+            .request(Request::Kill(
+                Container::new(name.to_string(), version.clone()),
+                signal,
+            ))
+            .await?
+        {
+            Response::Ok(()) => Ok(()),
+            Response::Err(e) => Err(Error::Api(e)),
+            _ => Err(Error::Protocol),
+        }
+    }
+
+    /// Execute a command in the namespaces of the started container with name. The
+    /// returned receiver yields the output and finally the exit status of the command.
+    ///
+    /// ```no_run
+    /// # use std::collections::HashMap;
+    /// # use tokio::time::Duration;
+    /// # use northstar::api::{client::Client, model::ExecOutput};
+    /// # use npk::manifest::Version;
+    /// #
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let mut client = Client::new(&url::Url::parse("tcp://localhost:4200").unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// let version = Version::parse("0.0.1").unwrap();
+    /// let args = ["/".to_string()];
+    /// let mut output = client.exec("hello", &version, "/bin/ls", &args, HashMap::new()).await.expect("Failed to exec");
+    /// while let Some(output) = output.recv().await {
+    ///     println!("{:?}", output);
+    /// }
+    /// # }
+    /// ```
+    pub async fn exec(
+        &self,
+        name: &str,
+        version: &Version,
+        cmd: &str,
+        args: &[String],
+        env: HashMap<String, String>,
+    ) -> Result<mpsc::Receiver<ExecOutput>, Error> {
+        let container = Container::new(name.to_string(), version.clone());
+        let request = Request::Exec(container, cmd.to_string(), args.to_vec(), env);
+        let (output_tx, output_rx) = mpsc::channel(100);
+        let (tx, rx) = oneshot::channel::<Result<Response, Error>>();
+        self.request_tx
+            .send((ClientRequest::Exec(request, output_tx), tx))
+            .await
+            .map_err(|_| Error::Stopped)?;
+        match rx.await.map_err(|_| Error::Stopped)?? {
+            Response::Ok(()) => Ok(output_rx),
+            Response::Err(e) => Err(Error::Api(e)),
+            _ => Err(Error::Protocol),
+        }
+    }
+
     /// Install a npk
     ///
     /// ```no_run
@@ -394,6 +572,41 @@ impl<'a> Client {
         }
     }
 
+    /// Update container with name to the npk `npk`. The npk is installed into the
+    /// repository of the container. A started container is replaced by the new version
+    /// and restored if the new version fails to start or exits within the health window.
+    /// The old version is uninstalled once the update completed unless other containers
+    /// use it as resource or dependency. For a started container this returns once the
+    /// new version is started. The completion or rollback of the update is reported with
+    /// a `Notification::Update`.
+    ///
+    /// ```no_run
+    /// # use northstar::api::client::Client;
+    /// # use npk::manifest::Version;
+    /// # use std::time::Duration;
+    /// # use std::path::Path;
+    /// #
+    /// # #[tokio::main]
+    /// # async fn main() {
+    /// #   let mut client = Client::new(&url::Url::parse("tcp://localhost:4200").unwrap(), None, Duration::from_secs(10)).await.unwrap();
+    /// let npk = Path::new("hello-0.0.2.npk");
+    /// client.update("hello", &Version::parse("0.0.1").unwrap(), &npk).await.expect("Failed to update \"hello\"");
+    /// # }
+    /// ```
+    pub async fn update(&self, name: &str, version: &Version, npk: &Path) -> Result<(), Error> {
+        let (tx, rx) = oneshot::channel::<Result<Response, Error>>();
+        let container = Container::new(name.to_string(), version.clone());
+        self.request_tx
+            .send((ClientRequest::Update(npk.to_owned(), container), tx))
+            .await
+            .map_err(|_| Error::Stopped)?;
+        match rx.await.map_err(|_| Error::Stopped)?? {
+            Response::Ok(()) => Ok(()),
+            Response::Err(e) => Err(Error::Api(e)),
+            _ => Err(Error::Protocol),
+        }
+    }
+
     /// Uninstall a npk
     ///
     /// ```no_run
@@ -521,3 +734,10 @@ impl Stream for Client {
         Pin::new(&mut self.notification_rx).poll_recv(cx)
     }
 }
+
+/// Open the npk at `npk` and return it along with its size
+async fn open(npk: &Path) -> Result<(fs::File, u64), io::Error> {
+    let file = fs::File::open(npk).await?;
+    let size = file.metadata().await?.len();
+    Ok((file, size))
+}
@@ -20,7 +20,7 @@ use std::{
     sync::Arc,
 };
 
-#[derive(Clone, Eq, PartialOrd, PartialEq, Debug, Hash, Serialize, Deserialize)]
+#[derive(Clone, Eq, Ord, PartialOrd, PartialEq, Debug, Hash, Serialize, Deserialize)]
 pub struct Container {
     #[serde(flatten)]
     inner: Arc<Inner>,
@@ -62,7 +62,7 @@ impl Display for Container {
     }
 }
 
-#[derive(Eq, PartialOrd, PartialEq, Debug, Hash, Serialize, Deserialize)]
+#[derive(Eq, Ord, PartialOrd, PartialEq, Debug, Hash, Serialize, Deserialize)]
 struct Inner {
     name: Name,
     version: Version,
@@ -14,16 +14,16 @@
 
 use derive_new::new;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 
-pub use npk::manifest::{Manifest, Version};
+pub use npk::manifest::{Manifest, OomAction, RLimitResource, RLimitValue, Version};
 pub type Container = super::container::Container;
 pub type MessageId = String; // UUID
 pub type Name = String;
 pub type Pid = u32;
 pub type RepositoryId = String;
 
-const VERSION: &str = "0.0.5";
+const VERSION: &str = "0.0.7";
 
 /// Protocol version
 /// TODO: Do some static initialization of the version struct
@@ -79,11 +79,14 @@ pub enum Payload {
     Request(Request),
     Response(Response),
     Notification(Notification),
+    /// Output of a command started with `Request::Exec`
+    Exec(ExecOutput),
 }
 
 #[derive(new, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Notification {
-    OutOfMemory(Container),
+    /// A container ran out of memory and the runtime applied the `OomAction`
+    OutOfMemory(Container, OomAction),
     Exit {
         container: Container,
         status: ExitStatus,
@@ -91,10 +94,39 @@ pub enum Notification {
     Install(Name, Version),
     Uninstalled(Name, Version),
     Started(Container),
+    /// Start of a container triggered by the runtime (e.g autostart) failed
+    StartFailed(Container, Error),
     Stopped(Container),
+    /// A container was frozen
+    Paused(Container),
+    /// A frozen container was thawed
+    Resumed(Container),
+    /// The runtime gave up restarting a container
+    CrashLoop(Container),
+    /// A update from container `from` to container `to` reached `phase`
+    Update {
+        from: Container,
+        to: Container,
+        phase: UpdatePhase,
+    },
     Shutdown,
 }
 
+/// Phase of a container update
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum UpdatePhase {
+    /// The new version is installed
+    Installed,
+    /// The old version is stopped
+    Stopped,
+    /// The new version is started and watched for the health window
+    Started,
+    /// The new version passed the health window
+    Completed,
+    /// The update failed and the old version is restored
+    RolledBack,
+}
+
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum Connect {
     Connect {
@@ -120,10 +152,33 @@ pub enum Request {
     Shutdown,
     Start(Container),
     /// Stop the given container. If the process does not exit within
-    /// the timeout in seconds it is SIGKILLED
-    Stop(Container, u64),
+    /// the timeout in seconds it is SIGKILLED. The stop signal and the
+    /// timeout if not set are taken from the manifest.
+    Stop(Container, Option<u64>),
     Umount(Container),
     Uninstall(Container),
+    /// Freeze all processes of the given container with the cgroup freezer
+    Pause(Container),
+    /// Thaw a paused container
+    Resume(Container),
+    /// Send a signal to the processes of the given container
+    Kill(Container, Signal),
+    /// Update the given container to the npk with the given size streamed after
+    /// the request. The npk is installed into the repository of the container.
+    Update(Container, u64),
+    /// Execute the command with the given arguments and environment in the
+    /// namespaces of the given started container. The output of the command
+    /// is streamed as `Payload::Exec` after the response.
+    Exec(Container, String, Vec<String>, HashMap<String, String>),
+}
+
+/// Output of a command executed in a container
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum ExecOutput {
+    Stdout(Vec<u8>),
+    Stderr(Vec<u8>),
+    /// The command exited. This is the last output of a command.
+    Exit(ExitStatus),
 }
 
 #[derive(new, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -141,14 +196,26 @@ pub struct Process {
     pub pid: Pid,
     /// Process uptime in nanoseconds
     pub uptime: u64,
+    /// The process is frozen
+    pub paused: bool,
     /// Resources used and allocated by this process
     pub resources: Resources,
+    /// Resource limits of this process
+    pub rlimits: HashMap<RLimitResource, RLimitValue>,
 }
 
-#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[derive(Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub struct Resources {
     /// Memory resources used by process
     pub memory: Option<Memory>,
+    /// Memory used by all processes of the container read from the memory cgroup
+    pub container_memory: Option<ContainerMemory>,
+    /// CPU time used by all processes of the container read from the cpu cgroup
+    pub cpu: Option<Cpu>,
+    /// Block IO of all processes of the container read from the io cgroup
+    pub io: Option<Io>,
+    /// Number of tasks in the container cgroup
+    pub pids: Option<u64>,
 }
 
 #[derive(new, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
@@ -160,6 +227,41 @@ pub struct Memory {
     pub data: u64,
 }
 
+/// Memory usage of a container cgroup in bytes
+#[derive(new, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ContainerMemory {
+    /// Current usage
+    pub current: u64,
+    /// Peak usage. Not available on cgroup v2 before Linux 5.19
+    pub peak: Option<u64>,
+}
+
+/// CPU usage of a container cgroup. Times are in microseconds.
+#[derive(new, Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Cpu {
+    /// Total CPU time
+    pub usage: Option<u64>,
+    /// CPU time in user mode
+    pub user: Option<u64>,
+    /// CPU time in kernel mode
+    pub system: Option<u64>,
+    /// Number of enforcement periods
+    pub periods: Option<u64>,
+    /// Number of periods the container was throttled in
+    pub throttled_periods: Option<u64>,
+    /// Total time the container was throttled
+    pub throttled_time: Option<u64>,
+}
+
+/// Block IO of a container cgroup in bytes summed over all devices
+#[derive(new, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub struct Io {
+    /// Bytes read
+    pub read: u64,
+    /// Bytes written
+    pub write: u64,
+}
+
 #[derive(new, Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 pub enum MountResult {
     Ok,
@@ -183,10 +285,27 @@ pub enum Error {
     StartContainerStarted(Container),
     StartContainerResource(Container),
     StartContainerMissingResource(Container, Container),
+    StartContainerMissingDependency(Container, Container),
     StartContainerFailed(Container, String),
     StopContainerNotStarted(Container),
+    PauseContainerNotStarted(Container),
+    PauseContainerPaused(Container),
+    PauseContainerUnsupported(Container),
+    ResumeContainerNotPaused(Container),
+    KillContainerNotStarted(Container),
+    KillContainerInvalidSignal(Container, Signal),
+    MountContainerMissingResource(Container, Container),
+    UpdateContainerMismatch(Container, Container),
+    UpdateContainerBusy(Container),
+    UpdateContainerUnhealthy(Container),
+    ExecContainerNotStarted(Container),
+    ExecContainerPaused(Container),
+    ExecContainerInvalidCommand(Container, String),
     InvalidRepository(RepositoryId),
     InstallDuplicate(Container),
+    InstallInvalidSeccomp(Container, String),
+    DependencyCycle(Vec<Container>),
+    Multiple(Vec<Error>),
 
     Npk(String, String),
     NpkArchive(String),
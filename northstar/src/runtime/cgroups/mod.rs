@@ -0,0 +1,381 @@
+// Copyright (c) 2019 - 2020 ESRLabs
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use super::{config, pipe::from_nix, Container, Event, EventTx, Pid};
+use crate::api::model::Resources;
+use log::{debug, warn};
+use nix::unistd;
+use npk::manifest;
+use proc_mounts::MountIter;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    os::unix::io::{AsRawFd, RawFd},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+use tokio::{fs, io, io::unix::AsyncFd, select, task, time};
+use tokio_util::sync::CancellationToken;
+
+mod v1;
+mod v2;
+
+/// Processes of a group. Present in both cgroup v1 and v2.
+const CGROUP_PROCS: &str = "cgroup.procs";
+/// Interval of freezer state polls
+const FREEZER_POLL_INTERVAL: time::Duration = time::Duration::from_millis(10);
+/// Maximum time to wait for the freezer to reach the requested state
+const FREEZER_TIMEOUT: time::Duration = time::Duration::from_secs(5);
+
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Failed to destroy: {0}: {1:?}")]
+    Destroy(String, io::Error),
+    #[error("Io error: {0}: {1:?}")]
+    Io(String, io::Error),
+    #[error("Failed to read mount info: {0:?}")]
+    MountInfo(#[source] io::Error),
+    #[error("Unknown cgroup controller {0}")]
+    UnknownController(String),
+    #[error("No cgroup hierarchy mounted")]
+    NoHierarchy,
+    #[error("Invalid cgroup configuration: {0}")]
+    Configuration(String),
+    #[error("No freezer cgroup")]
+    NoFreezer,
+    #[error("Timeout waiting for {0}")]
+    Timeout(String),
+}
+
+/// CGroups of a container. The backend is selected from the mounted cgroup
+/// hierarchies: if any cgroup v1 controller hierarchy is mounted v1 is used.
+/// Otherwise the unified cgroup v2 hierarchy is used.
+#[derive(Debug)]
+pub enum CGroups {
+    V1(v1::CGroups),
+    V2(v2::CGroups),
+}
+
+/// Parameters written to the group of a controller in order. Controllers without
+/// parameters are used without changing their defaults.
+type Parameters = BTreeMap<String, Vec<(String, String)>>;
+
+/// Mounted cgroup hierarchy
+enum Hierarchy {
+    /// Per controller cgroup v1 hierarchies
+    Legacy,
+    /// Unified cgroup v2 hierarchy mounted at the contained path
+    Unified(PathBuf),
+}
+
+impl CGroups {
+    pub(super) async fn new(
+        configuration: &config::CGroups,
+        container: &Container,
+        cgroups: &manifest::CGroups,
+        oom: manifest::OomAction,
+        tx: EventTx,
+    ) -> Result<CGroups, Error> {
+        match hierarchy()? {
+            Hierarchy::Legacy => {
+                debug!("Using cgroup v1 for {}", container);
+                v1::CGroups::new(configuration, container, cgroups, oom, tx)
+                    .await
+                    .map(CGroups::V1)
+            }
+            Hierarchy::Unified(mount_point) => {
+                debug!("Using cgroup v2 for {}", container);
+                v2::CGroups::new(&mount_point, configuration, container, cgroups, oom, tx)
+                    .await
+                    .map(CGroups::V2)
+            }
+        }
+    }
+
+    pub async fn assign(&self, pid: Pid) -> Result<(), Error> {
+        match self {
+            CGroups::V1(cgroups) => cgroups.assign(pid).await,
+            CGroups::V2(cgroups) => cgroups.assign(pid).await,
+        }
+    }
+
+    pub async fn destroy(self) -> Result<(), Error> {
+        match self {
+            CGroups::V1(cgroups) => cgroups.destroy().await,
+            CGroups::V2(cgroups) => cgroups.destroy().await,
+        }
+    }
+
+    /// Freeze all processes of the container and wait until they are frozen
+    pub async fn freeze(&self) -> Result<(), Error> {
+        match self {
+            CGroups::V1(cgroups) => cgroups.freeze().await,
+            CGroups::V2(cgroups) => cgroups.freeze().await,
+        }
+    }
+
+    /// Thaw all processes of the container
+    pub async fn thaw(&self) -> Result<(), Error> {
+        match self {
+            CGroups::V1(cgroups) => cgroups.thaw().await,
+            CGroups::V2(cgroups) => cgroups.thaw().await,
+        }
+    }
+
+    /// Resources used by the container. Values of controllers that are not
+    /// used by the container are not set. The process memory is not set.
+    pub fn resources(&self) -> Resources {
+        match self {
+            CGroups::V1(cgroups) => cgroups.resources(),
+            CGroups::V2(cgroups) => cgroups.resources(),
+        }
+    }
+}
+
+/// Find the groups of containers left over from a previous runtime instance in the
+/// configured cgroup directories. Empty groups are removed. Groups that still contain
+/// processes are returned with the pids of these orphaned processes.
+pub(super) async fn orphans(
+    configuration: &config::CGroups,
+) -> Result<Vec<(PathBuf, Vec<Pid>)>, Error> {
+    // Never scan the root of a hierarchy
+    let dirs = configuration
+        .iter()
+        .filter(|(_, dir)| dir.file_name().is_some());
+    let roots = match hierarchy()? {
+        Hierarchy::Legacy => dirs
+            .filter_map(|(controller, dir)| v1::mount_point(controller).ok().map(|m| m.join(dir)))
+            .collect::<BTreeSet<_>>(),
+        Hierarchy::Unified(mount_point) => dirs.map(|(_, dir)| mount_point.join(dir)).collect(),
+    };
+
+    orphans_in(&roots).await
+}
+
+/// Find the container groups within `roots`. Empty groups are removed and the others
+/// are returned with their pids.
+async fn orphans_in(roots: &BTreeSet<PathBuf>) -> Result<Vec<(PathBuf, Vec<Pid>)>, Error> {
+    let mut orphans = Vec::new();
+    for root in roots.iter().filter(|root| root.exists()) {
+        let mut entries = fs::read_dir(root)
+            .await
+            .map_err(|e| Error::Io(format!("Failed to read {}", root.display()), e))?;
+        while let Some(entry) = entries
+            .next_entry()
+            .await
+            .map_err(|e| Error::Io(format!("Failed to read {}", root.display()), e))?
+        {
+            let group = entry.path();
+            if !group.is_dir() {
+                continue;
+            }
+            let pids = read(&group.join(CGROUP_PROCS))
+                .unwrap_or_default()
+                .lines()
+                .filter_map(|pid| pid.trim().parse().ok())
+                .collect::<Vec<Pid>>();
+            if pids.is_empty() {
+                debug!("Removing leftover cgroup {}", group.display());
+                if let Err(e) = fs::remove_dir(&group).await {
+                    warn!("Failed to remove {}: {}", group.display(), e);
+                }
+            } else {
+                orphans.push((group, pids));
+            }
+        }
+    }
+    Ok(orphans)
+}
+
+/// Select the cgroup hierarchy from /proc/mounts
+fn hierarchy() -> Result<Hierarchy, Error> {
+    let mut unified = None;
+    for mount in MountIter::new().map_err(Error::MountInfo)?.flatten() {
+        match mount.fstype.as_str() {
+            "cgroup" => return Ok(Hierarchy::Legacy),
+            "cgroup2" if unified.is_none() => unified = Some(mount.dest),
+            _ => (),
+        }
+    }
+    unified.map(Hierarchy::Unified).ok_or(Error::NoHierarchy)
+}
+
+async fn write(path: &Path, value: &str) -> Result<(), Error> {
+    fs::write(path, value)
+        .await
+        .map_err(|e| Error::Io(format!("Failed to write to {}", path.display()), e))
+}
+
+/// Return the value of `key` in a flat keyed cgroup file like memory.events
+fn field(content: &str, key: &str) -> Option<u64> {
+    content
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(k, _)| *k == key)
+        .and_then(|(_, v)| v.trim().parse().ok())
+}
+
+/// Return the value of the counter `key` in a flat keyed cgroup file or 0 if not present
+fn counter(content: &str, key: &str) -> u64 {
+    field(content, key).unwrap_or(0)
+}
+
+/// Read the cgroup file `path`. Statistics are read synchronously because cgroup files
+/// are generated by the kernel and never block.
+fn read(path: &Path) -> Option<String> {
+    std::fs::read_to_string(path).ok()
+}
+
+/// Read a single value cgroup file
+fn value(path: &Path) -> Option<u64> {
+    read(path)?.trim().parse().ok()
+}
+
+/// Return the number of lines of the cgroup file `path`
+fn lines(path: &Path) -> Option<u64> {
+    read(path).map(|content| content.lines().count() as u64)
+}
+
+/// Poll `path` until `done` returns true for its content. Freezer state changes
+/// are not notified on cgroup v1 and the state is polled for both versions.
+async fn wait(path: &Path, done: impl Fn(&str) -> bool) -> Result<(), Error> {
+    let poll = async {
+        loop {
+            let content = fs::read_to_string(path)
+                .await
+                .map_err(|e| Error::Io(format!("Failed to read {}", path.display()), e))?;
+            if done(&content) {
+                break Ok(());
+            }
+            time::sleep(FREEZER_POLL_INTERVAL).await;
+        }
+    };
+    time::timeout(FREEZER_TIMEOUT, poll)
+        .await
+        .map_err(|_| Error::Timeout(path.display().to_string()))?
+}
+
+/// File descriptor that is closed on drop
+#[derive(Debug)]
+struct Fd(RawFd);
+
+impl AsRawFd for Fd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Drop for Fd {
+    fn drop(&mut self) {
+        unistd::close(self.0).ok();
+    }
+}
+
+/// Spawn a task that waits for notifications on the nonblocking `fd`. The fd is
+/// an eventfd registered for memory.oom_control (v1) or an inotify instance watching
+/// memory.events (v2). On each notification `path` is read and `Event::Oom` is sent
+/// if `is_oom` reports a new oom condition. The task stops if `path` is gone, the main
+/// loop receiver closes or `stop` is cancelled.
+fn oom_monitor(
+    container: Container,
+    fd: Fd,
+    path: PathBuf,
+    mut is_oom: impl FnMut(&str) -> bool + Send + 'static,
+    tx: EventTx,
+    stop: CancellationToken,
+) -> Result<(), Error> {
+    let fd =
+        AsyncFd::new(fd).map_err(|e| Error::Io("Failed to register oom notification".into(), e))?;
+
+    task::spawn(async move {
+        loop {
+            select! {
+                _ = stop.cancelled() => break,
+                _ = tx.closed() => break,
+                guard = fd.readable() => {
+                    let mut guard = match guard {
+                        Ok(guard) => guard,
+                        Err(e) => {
+                            warn!("Failed to poll oom notification of {}: {}", container, e);
+                            break;
+                        }
+                    };
+                    // Consume the eventfd counter or pending inotify events
+                    let mut buffer = [0u8; 4096];
+                    match guard.try_io(|fd| unistd::read(fd.as_raw_fd(), &mut buffer).map_err(from_nix)) {
+                        Ok(Ok(_)) => (),
+                        Ok(Err(e)) => {
+                            warn!("Failed to read oom notification of {}: {}", container, e);
+                            break;
+                        }
+                        Err(_would_block) => continue,
+                    }
+
+                    match fs::read_to_string(&path).await {
+                        Ok(content) if is_oom(&content) => {
+                            warn!("Container {} is under OOM!", container);
+                            tx.send(Event::Oom(container.clone())).await.ok();
+                        }
+                        Ok(_) => (),
+                        Err(_) => {
+                            debug!("Stopping oom monitor of {}", container);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    });
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{counter, field, orphans_in, CGROUP_PROCS};
+    use std::{collections::BTreeSet, fs};
+
+    #[test]
+    fn counters() {
+        let events = "low 0\nhigh 2\nmax 3\noom 1\noom_kill 0\n";
+        assert_eq!(counter(events, "high"), 2);
+        assert_eq!(counter(events, "oom"), 1);
+        assert_eq!(counter(events, "oom_kill"), 0);
+        assert_eq!(counter(events, "oom_group_kill"), 0);
+        assert_eq!(field(events, "max"), Some(3));
+        assert_eq!(field(events, "oom_group_kill"), None);
+    }
+
+    #[tokio::test]
+    async fn orphans() {
+        let tmpdir = tempfile::TempDir::new().unwrap();
+        let root = tmpdir.path().join("northstar");
+        let empty = root.join("hello:0.0.1");
+        let orphan = root.join("crashed:0.0.1");
+        fs::create_dir_all(&empty).unwrap();
+        fs::create_dir_all(&orphan).unwrap();
+        fs::write(orphan.join(CGROUP_PROCS), "12\n34\n").unwrap();
+        fs::write(root.join(CGROUP_PROCS), "1\n").unwrap();
+
+        let mut roots = BTreeSet::new();
+        roots.insert(root.clone());
+        // Roots that do not exist are skipped
+        roots.insert(tmpdir.path().join("missing"));
+
+        let orphans = orphans_in(&roots).await.unwrap();
+        assert_eq!(orphans, vec![(orphan.clone(), vec![12, 34])]);
+        assert!(!empty.exists());
+        assert!(orphan.exists());
+        assert!(root.join(CGROUP_PROCS).exists());
+    }
+}
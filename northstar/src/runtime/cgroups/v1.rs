@@ -0,0 +1,452 @@
+// Copyright (c) 2019 - 2020 ESRLabs
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use super::{
+    super::{config, pipe::from_nix, Container, EventTx, Pid},
+    counter, field, lines, oom_monitor, read, value, wait, write, Error, Fd, Parameters,
+};
+use crate::api::model::{ContainerMemory, Cpu, Io, Resources};
+use log::debug;
+use nix::{
+    fcntl::{self, OFlag},
+    sys::{
+        eventfd::{eventfd, EfdFlags},
+        stat::Mode,
+    },
+    unistd::{self, SysconfVar},
+};
+use npk::manifest;
+use proc_mounts::MountIter;
+use std::{
+    collections::HashMap,
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+use tokio_util::sync::CancellationToken;
+
+const BLKIO_SERVICE_BYTES: &str = "blkio.throttle.io_service_bytes";
+const CPU_STAT: &str = "cpu.stat";
+const CPUSET_CPUS: &str = "cpuset.cpus";
+const CPUSET_MEMS: &str = "cpuset.mems";
+const CPUACCT_STAT: &str = "cpuacct.stat";
+const CPUACCT_USAGE: &str = "cpuacct.usage";
+const DEFAULT_CPU_PERIOD: u64 = 100_000;
+const EVENT_CONTROL: &str = "cgroup.event_control";
+const FREEZER_STATE: &str = "freezer.state";
+const FROZEN: &str = "FROZEN";
+const MEMORY_MAX_USAGE: &str = "memory.max_usage_in_bytes";
+const MEMORY_USAGE: &str = "memory.usage_in_bytes";
+const OOM_CONTROL: &str = "memory.oom_control";
+const PIDS_CURRENT: &str = "pids.current";
+const OOM_KILL: &str = "oom_kill";
+const UNDER_OOM: &str = "under_oom";
+const TASKS: &str = "tasks";
+const THAWED: &str = "THAWED";
+
+/// CGroups of a container in the cgroup v1 controller hierarchies
+#[derive(Debug)]
+pub struct CGroups {
+    /// Group directories by controller
+    groups: HashMap<String, PathBuf>,
+    stop: CancellationToken,
+}
+
+impl CGroups {
+    pub(super) async fn new(
+        configuration: &config::CGroups,
+        container: &Container,
+        cgroups: &manifest::CGroups,
+        oom: manifest::OomAction,
+        tx: EventTx,
+    ) -> Result<CGroups, Error> {
+        let mut groups = HashMap::new();
+        let stop = CancellationToken::new();
+
+        // The freezer is used for every container if configured
+        let mut parameters = parameters(cgroups);
+        if configuration.contains_key("freezer") {
+            parameters.entry("freezer".into()).or_default();
+        }
+
+        for (controller, params) in parameters {
+            let mount_point = mount_point(&controller)?;
+            let subdir = configuration
+                .get(&controller)
+                .ok_or_else(|| Error::UnknownController(controller.clone()))?;
+            let path = mount_point.join(subdir).join(container.name());
+
+            // Create cgroup
+            if !path.exists() {
+                debug!("Creating {}", path.display());
+                fs::create_dir_all(&path)
+                    .await
+                    .map_err(|e| Error::Io(format!("Failed to create {}", path.display()), e))?;
+            }
+
+            // Tasks cannot be attached to a cpuset without cpus and mems
+            if controller == "cpuset" {
+                init_cpuset(&mount_point, &path).await?;
+            }
+
+            // Apply settings from manifest for this group
+            for (param, value) in params {
+                let filename = path.join(format!("{}.{}", controller, param));
+                debug!("Setting {} to {}", filename.display(), value);
+                write(&filename, &value).await?;
+            }
+
+            // Start a monitor for the memory controller
+            if controller == "memory" {
+                memory_monitor(container.clone(), &path, oom, tx.clone(), stop.clone()).await?;
+            }
+
+            groups.insert(controller, path);
+        }
+
+        Ok(CGroups { groups, stop })
+    }
+
+    pub(super) async fn assign(&self, pid: Pid) -> Result<(), Error> {
+        for cgroup_dir in self.groups.values() {
+            let tasks = cgroup_dir.join(TASKS);
+            debug!("Assigning {} to {}", pid, tasks.display());
+            write(&tasks, &pid.to_string()).await?;
+        }
+        Ok(())
+    }
+
+    pub(super) async fn destroy(self) -> Result<(), Error> {
+        self.stop.cancel();
+        for cgroup_dir in self.groups.values() {
+            debug!("Destroying CGroup {}", cgroup_dir.display());
+            fs::remove_dir(&cgroup_dir)
+                .await
+                .map_err(|e| Error::Destroy(cgroup_dir.display().to_string(), e))?;
+        }
+        Ok(())
+    }
+
+    pub(super) async fn freeze(&self) -> Result<(), Error> {
+        let state = self.freezer()?.join(FREEZER_STATE);
+        debug!("Freezing {}", state.display());
+        write(&state, FROZEN).await?;
+        wait(&state, |s| s.trim() == FROZEN).await
+    }
+
+    pub(super) async fn thaw(&self) -> Result<(), Error> {
+        let state = self.freezer()?.join(FREEZER_STATE);
+        debug!("Thawing {}", state.display());
+        write(&state, THAWED).await?;
+        wait(&state, |s| s.trim() == THAWED).await
+    }
+
+    /// Group of the freezer controller
+    fn freezer(&self) -> Result<&PathBuf, Error> {
+        self.groups.get("freezer").ok_or(Error::NoFreezer)
+    }
+
+    pub(super) fn resources(&self) -> Resources {
+        let container_memory = self.groups.get("memory").and_then(|dir| {
+            Some(ContainerMemory {
+                current: value(&dir.join(MEMORY_USAGE))?,
+                peak: value(&dir.join(MEMORY_MAX_USAGE)),
+            })
+        });
+
+        // The cpuacct controller is usually mounted together with cpu
+        let cpu = self
+            .groups
+            .get("cpu")
+            .or_else(|| self.groups.get("cpuacct"))
+            .map(|dir| {
+                let stat = read(&dir.join(CPU_STAT)).unwrap_or_default();
+                let acct = read(&dir.join(CPUACCT_STAT)).unwrap_or_default();
+                Cpu {
+                    usage: value(&dir.join(CPUACCT_USAGE)).map(|ns| ns / 1000),
+                    user: field(&acct, "user").and_then(ticks_to_us),
+                    system: field(&acct, "system").and_then(ticks_to_us),
+                    periods: field(&stat, "nr_periods"),
+                    throttled_periods: field(&stat, "nr_throttled"),
+                    throttled_time: field(&stat, "throttled_time").map(|ns| ns / 1000),
+                }
+            });
+
+        let io = self
+            .groups
+            .get("blkio")
+            .and_then(|dir| read(&dir.join(BLKIO_SERVICE_BYTES)))
+            .map(|content| io(&content));
+
+        // Without the pids controller the tasks of any group are counted
+        let pids = self
+            .groups
+            .get("pids")
+            .and_then(|dir| value(&dir.join(PIDS_CURRENT)))
+            .or_else(|| {
+                self.groups
+                    .values()
+                    .next()
+                    .and_then(|dir| lines(&dir.join(TASKS)))
+            });
+
+        Resources {
+            memory: None,
+            container_memory,
+            cpu,
+            io,
+            pids,
+        }
+    }
+}
+
+/// Translate the manifest cgroup configuration to cgroup v1 parameters
+fn parameters(cgroups: &manifest::CGroups) -> Parameters {
+    let mut parameters = Parameters::new();
+
+    if let Some(memory) = cgroups.memory.as_ref() {
+        let params = parameters.entry("memory".into()).or_default();
+        if let Some(limit) = memory.limit {
+            params.push(("limit_in_bytes".into(), limit.to_string()));
+        }
+    }
+
+    if let Some(cpu) = cgroups.cpu.as_ref() {
+        let params = parameters.entry("cpu".into()).or_default();
+        if let Some(shares) = cpu.shares {
+            params.push(("shares".into(), shares.to_string()));
+        }
+        if let Some(quota) = cpu.quota {
+            let period = cpu.period.unwrap_or(DEFAULT_CPU_PERIOD);
+            params.push(("cfs_period_us".into(), period.to_string()));
+            params.push(("cfs_quota_us".into(), quota.to_string()));
+        }
+    }
+
+    if let Some(pids) = cgroups.pids.as_ref() {
+        let params = parameters.entry("pids".into()).or_default();
+        if let Some(max) = pids.max {
+            params.push(("max".into(), max.to_string()));
+        }
+    }
+
+    if let Some(io) = cgroups.io.as_ref() {
+        let params = parameters.entry("blkio".into()).or_default();
+        if let Some(weight) = io.weight {
+            // Conversion of the weight range [1, 10000] to the blkio range [10, 1000]
+            let weight = 10 + (weight as u64 - 1) * 990 / 9999;
+            params.push(("weight".into(), weight.to_string()));
+        }
+    }
+
+    if let Some(cpuset) = cgroups.cpuset.as_ref() {
+        let params = parameters.entry("cpuset".into()).or_default();
+        if let Some(cpus) = cpuset.cpus.as_ref() {
+            params.push(("cpus".into(), cpus.clone()));
+        }
+        if let Some(mems) = cpuset.mems.as_ref() {
+            params.push(("mems".into(), mems.clone()));
+        }
+    }
+
+    for (controller, params) in &cgroups.raw {
+        parameters
+            .entry(controller.clone())
+            .or_default()
+            .extend(params.iter().map(|(p, v)| (p.clone(), v.clone())));
+    }
+
+    parameters
+}
+
+/// Copy cpuset.cpus and cpuset.mems from the parent to each group from the
+/// hierarchy root down to `path` that has no cpus or mems assigned
+async fn init_cpuset(mount_point: &Path, path: &Path) -> Result<(), Error> {
+    let relative = path.strip_prefix(mount_point).unwrap_or(path);
+    let mut parent = mount_point.to_owned();
+    for component in relative.components() {
+        let dir = parent.join(component);
+        for file in &[CPUSET_CPUS, CPUSET_MEMS] {
+            let current = read(&dir.join(file)).unwrap_or_default();
+            if current.trim().is_empty() {
+                let inherited = read(&parent.join(file)).unwrap_or_default();
+                write(&dir.join(file), inherited.trim()).await?;
+            }
+        }
+        parent = dir;
+    }
+    Ok(())
+}
+
+/// Convert clock ticks to microseconds
+fn ticks_to_us(ticks: u64) -> Option<u64> {
+    let ticks_per_second = unistd::sysconf(SysconfVar::CLK_TCK).ok()?? as u64;
+    Some(ticks * 1_000_000 / ticks_per_second)
+}
+
+/// Sum the bytes read and written from blkio.throttle.io_service_bytes. Lines have
+/// the format "MAJOR:MINOR OPERATION BYTES" followed by a "Total BYTES" line.
+fn io(content: &str) -> Io {
+    content
+        .lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>())
+        .fold(Io::new(0, 0), |mut io, line| {
+            if let [_, operation, bytes] = line.as_slice() {
+                let bytes = bytes.parse::<u64>().unwrap_or(0);
+                match *operation {
+                    "Read" => io.read += bytes,
+                    "Write" => io.write += bytes,
+                    _ => (),
+                }
+            }
+            io
+        })
+}
+
+/// Register an eventfd for oom notifications of the memory cgroup at `path`
+/// and report a oom condition in case. The kernel oom killer is disabled for
+/// `OomAction::Stop` and `OomAction::Restart` where the runtime stops the stalled
+/// container.
+async fn memory_monitor(
+    container: Container,
+    path: &Path,
+    oom: manifest::OomAction,
+    tx: EventTx,
+    stop: CancellationToken,
+) -> Result<(), Error> {
+    // Configure oom
+    let oom_control = path.join(OOM_CONTROL);
+    // A disabled killer stalls the container until memory is freed. Reported
+    // containers would stall forever.
+    let disable = matches!(
+        oom,
+        manifest::OomAction::Stop | manifest::OomAction::Restart
+    );
+    write(&oom_control, if disable { "1" } else { "0" }).await?;
+
+    // The registration keeps no reference to the oom_control fd. It is closed
+    // when the registration is written.
+    let efd = eventfd(0, EfdFlags::EFD_NONBLOCK | EfdFlags::EFD_CLOEXEC)
+        .map(Fd)
+        .map_err(|e| Error::Io("Failed to create eventfd".into(), from_nix(e)))?;
+    let oom_control_fd = fcntl::open(
+        &oom_control,
+        OFlag::O_RDONLY | OFlag::O_CLOEXEC,
+        Mode::empty(),
+    )
+    .map(Fd)
+    .map_err(|e| {
+        Error::Io(
+            format!("Failed to open {}", oom_control.display()),
+            from_nix(e),
+        )
+    })?;
+    let event_control = path.join(EVENT_CONTROL);
+    let registration = format!("{} {}", efd.as_raw_fd(), oom_control_fd.as_raw_fd());
+    write(&event_control, &registration).await?;
+    drop(oom_control_fd);
+
+    // Report a container entering the oom state or a kill by the kernel oom killer
+    let mut under_oom = false;
+    let mut kills = 0;
+    let is_oom = move |oom_control: &str| {
+        let under = counter(oom_control, UNDER_OOM) == 1;
+        let killed = counter(oom_control, OOM_KILL);
+        let oom = (under && !under_oom) || killed > kills;
+        under_oom = under;
+        kills = killed;
+        oom
+    };
+
+    oom_monitor(container, efd, oom_control, is_oom, tx, stop)
+}
+
+/// Get the cgroup v1 controller hierarchy mount point
+pub(super) fn mount_point(controller: &str) -> Result<PathBuf, Error> {
+    let controller = controller.to_owned();
+    MountIter::new()
+        .map_err(Error::MountInfo)?
+        .filter_map(Result::ok)
+        .find(|m| m.fstype == "cgroup" && m.options.contains(&controller))
+        .map(|m| m.dest)
+        .ok_or(Error::UnknownController(controller))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{io, parameters};
+    use crate::api::model::Io;
+    use npk::manifest::{CGroups, CpuCGroup, IoCGroup, MemoryCGroup};
+    use std::collections::HashMap;
+
+    #[test]
+    fn translate() {
+        let mut raw = HashMap::new();
+        raw.insert(
+            "memory".to_string(),
+            vec![("swappiness".to_string(), "0".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        let cgroups = CGroups {
+            memory: Some(MemoryCGroup { limit: Some(1000) }),
+            cpu: Some(CpuCGroup {
+                shares: Some(1024),
+                quota: Some(50000),
+                period: None,
+            }),
+            io: Some(IoCGroup {
+                weight: Some(10000),
+            }),
+            raw,
+            ..Default::default()
+        };
+        let parameters = parameters(&cgroups);
+        let params = |c: &str| {
+            parameters[c]
+                .iter()
+                .map(|(p, v)| (p.as_str(), v.as_str()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            params("memory"),
+            vec![("limit_in_bytes", "1000"), ("swappiness", "0")]
+        );
+        assert_eq!(
+            params("cpu"),
+            vec![
+                ("shares", "1024"),
+                ("cfs_period_us", "100000"),
+                ("cfs_quota_us", "50000")
+            ]
+        );
+        assert_eq!(params("blkio"), vec![("weight", "1000")]);
+        assert_eq!(parameters.len(), 3);
+    }
+
+    #[test]
+    fn io_service_bytes() {
+        let content = "8:0 Read 4096
+8:0 Write 1024
+8:0 Sync 5120
+8:0 Async 0
+8:0 Total 5120
+8:16 Read 100
+8:16 Write 200
+Total 5420
+";
+        assert_eq!(io(content), Io::new(4196, 1224));
+        assert_eq!(io(""), Io::new(0, 0));
+    }
+}
@@ -0,0 +1,474 @@
+// Copyright (c) 2021 ESRLabs
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use super::{
+    super::{config, pipe::from_nix, Container, EventTx, Pid},
+    counter, field, lines, oom_monitor, read, value, wait, write, Error, Fd, Parameters,
+};
+use crate::api::model::{ContainerMemory, Cpu, Io, Resources};
+use log::debug;
+use nix::sys::inotify::{AddWatchFlags, InitFlags, Inotify};
+use npk::manifest;
+use std::{
+    os::unix::io::AsRawFd,
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+use tokio::fs;
+use tokio_util::sync::CancellationToken;
+
+const CGROUP_EVENTS: &str = "cgroup.events";
+const CGROUP_FREEZE: &str = "cgroup.freeze";
+const CGROUP_PROCS: &str = "cgroup.procs";
+const CGROUP_THREADS: &str = "cgroup.threads";
+const CPU_STAT: &str = "cpu.stat";
+const IO_STAT: &str = "io.stat";
+const MEMORY_CURRENT: &str = "memory.current";
+const MEMORY_EVENTS: &str = "memory.events";
+const MEMORY_HIGH: &str = "high";
+const MEMORY_PEAK: &str = "memory.peak";
+const OOM: &str = "oom";
+const PIDS_CURRENT: &str = "pids.current";
+const DEFAULT_CPU_PERIOD: u64 = 100_000;
+const HIGH_REPORT_INTERVAL: Duration = Duration::from_secs(1);
+const SUBTREE_CONTROL: &str = "cgroup.subtree_control";
+
+/// CGroup of a container in the unified cgroup v2 hierarchy. All controllers
+/// share a single group.
+#[derive(Debug)]
+pub struct CGroups {
+    group: PathBuf,
+    stop: CancellationToken,
+}
+
+impl CGroups {
+    pub(super) async fn new(
+        mount_point: &Path,
+        configuration: &config::CGroups,
+        container: &Container,
+        cgroups: &manifest::CGroups,
+        oom: manifest::OomAction,
+        tx: EventTx,
+    ) -> Result<CGroups, Error> {
+        let stop = CancellationToken::new();
+        let parameters = parameters(cgroups, oom);
+
+        // A process is member of exactly one group in the unified hierarchy. All
+        // controllers used by the container must be configured with the same root.
+        // The freezer is no controller on cgroup v2 but a configured freezer
+        // directory is used as root as well.
+        let mut root: Option<&PathBuf> = None;
+        let freezer = configuration.get_key_value("freezer");
+        for controller in parameters.keys().chain(freezer.map(|(k, _)| k)) {
+            let subdir = configuration
+                .get(controller)
+                .ok_or_else(|| Error::UnknownController(controller.into()))?;
+            match root {
+                Some(root) if root != subdir => {
+                    return Err(Error::Configuration(format!(
+                        "cgroup v2 requires the same directory for all controllers: {} and {}",
+                        root.display(),
+                        subdir.display()
+                    )))
+                }
+                _ => root = Some(subdir),
+            }
+        }
+        let root = root.cloned().unwrap_or_default();
+
+        // Enable the controllers for all groups from the mount point down to the
+        // root of the container groups
+        let mut dir = mount_point.to_owned();
+        enable_controllers(&dir, parameters.keys()).await?;
+        for component in root.components() {
+            dir = dir.join(component);
+            create(&dir).await?;
+            enable_controllers(&dir, parameters.keys()).await?;
+        }
+
+        let group = dir.join(container.name());
+        create(&group).await?;
+
+        // Apply settings from manifest
+        for (controller, params) in &parameters {
+            for (param, value) in params {
+                let filename = group.join(format!("{}.{}", controller, param));
+                debug!("Setting {} to {}", filename.display(), value);
+                write(&filename, &value).await?;
+            }
+        }
+
+        // Start a monitor for the memory controller
+        if parameters.contains_key("memory") {
+            memory_monitor(container.clone(), &group, oom, tx, stop.clone())?;
+        }
+
+        Ok(CGroups { group, stop })
+    }
+
+    pub(super) async fn assign(&self, pid: Pid) -> Result<(), Error> {
+        let procs = self.group.join(CGROUP_PROCS);
+        debug!("Assigning {} to {}", pid, procs.display());
+        write(&procs, &pid.to_string()).await
+    }
+
+    pub(super) async fn destroy(self) -> Result<(), Error> {
+        self.stop.cancel();
+        debug!("Destroying CGroup {}", self.group.display());
+        fs::remove_dir(&self.group)
+            .await
+            .map_err(|e| Error::Destroy(self.group.display().to_string(), e))
+    }
+
+    pub(super) async fn freeze(&self) -> Result<(), Error> {
+        let freeze = self.group.join(CGROUP_FREEZE);
+        debug!("Freezing {}", freeze.display());
+        write(&freeze, "1").await?;
+        wait(&self.group.join(CGROUP_EVENTS), |events| {
+            field(events, "frozen") == Some(1)
+        })
+        .await
+    }
+
+    pub(super) async fn thaw(&self) -> Result<(), Error> {
+        let freeze = self.group.join(CGROUP_FREEZE);
+        debug!("Thawing {}", freeze.display());
+        write(&freeze, "0").await?;
+        wait(&self.group.join(CGROUP_EVENTS), |events| {
+            field(events, "frozen") == Some(0)
+        })
+        .await
+    }
+
+    pub(super) fn resources(&self) -> Resources {
+        let dir = &self.group;
+
+        let container_memory = value(&dir.join(MEMORY_CURRENT)).map(|current| ContainerMemory {
+            current,
+            peak: value(&dir.join(MEMORY_PEAK)),
+        });
+
+        // cpu.stat is present in every group. The throttling counters are only
+        // present if the cpu controller is enabled.
+        let cpu = read(&dir.join(CPU_STAT)).map(|stat| Cpu {
+            usage: field(&stat, "usage_usec"),
+            user: field(&stat, "user_usec"),
+            system: field(&stat, "system_usec"),
+            periods: field(&stat, "nr_periods"),
+            throttled_periods: field(&stat, "nr_throttled"),
+            throttled_time: field(&stat, "throttled_usec"),
+        });
+
+        let io = read(&dir.join(IO_STAT)).map(|content| io(&content));
+
+        // Without the pids controller the threads of the group are counted
+        let pids = value(&dir.join(PIDS_CURRENT)).or_else(|| lines(&dir.join(CGROUP_THREADS)));
+
+        Resources {
+            memory: None,
+            container_memory,
+            cpu,
+            io,
+            pids,
+        }
+    }
+}
+
+/// Sum the bytes read and written from io.stat. Lines have the format
+/// "MAJOR:MINOR rbytes=BYTES wbytes=BYTES rios=IOS ..."
+fn io(content: &str) -> Io {
+    content
+        .split_whitespace()
+        .filter_map(|pair| pair.split_once('='))
+        .fold(Io::new(0, 0), |mut io, (key, value)| {
+            let value = value.parse::<u64>().unwrap_or(0);
+            match key {
+                "rbytes" => io.read += value,
+                "wbytes" => io.write += value,
+                _ => (),
+            }
+            io
+        })
+}
+
+/// Create the group `dir` if it does not exist
+async fn create(dir: &Path) -> Result<(), Error> {
+    if !dir.exists() {
+        debug!("Creating {}", dir.display());
+        fs::create_dir_all(dir)
+            .await
+            .map_err(|e| Error::Io(format!("Failed to create {}", dir.display()), e))?;
+    }
+    Ok(())
+}
+
+/// Enable `controllers` for the children of the group `dir`
+async fn enable_controllers(
+    dir: &Path,
+    controllers: impl Iterator<Item = &String>,
+) -> Result<(), Error> {
+    let subtree_control = dir.join(SUBTREE_CONTROL);
+    for controller in controllers {
+        write(&subtree_control, &format!("+{}", controller)).await?;
+    }
+    Ok(())
+}
+
+/// Translate the manifest cgroup configuration to cgroup v2 parameters. The memory
+/// limit is enforced by the kernel oom killer with `OomAction::Kill` only. With any
+/// other action the limit is applied as memory.high and throttles the group until
+/// the runtime applied the action.
+fn parameters(cgroups: &manifest::CGroups, oom: manifest::OomAction) -> Parameters {
+    let mut parameters = Parameters::new();
+
+    if let Some(memory) = cgroups.memory.as_ref() {
+        let params = parameters.entry("memory".into()).or_default();
+        if let Some(limit) = memory.limit {
+            params.push(("max".into(), limit.to_string()));
+        }
+    }
+
+    if let Some(cpu) = cgroups.cpu.as_ref() {
+        let params = parameters.entry("cpu".into()).or_default();
+        if let Some(shares) = cpu.shares {
+            params.push(("weight".into(), shares_to_weight(shares).to_string()));
+        }
+        if let Some(quota) = cpu.quota {
+            let period = cpu.period.unwrap_or(DEFAULT_CPU_PERIOD);
+            params.push(("max".into(), format!("{} {}", quota, period)));
+        }
+    }
+
+    if let Some(pids) = cgroups.pids.as_ref() {
+        let params = parameters.entry("pids".into()).or_default();
+        if let Some(max) = pids.max {
+            params.push(("max".into(), max.to_string()));
+        }
+    }
+
+    if let Some(io) = cgroups.io.as_ref() {
+        let params = parameters.entry("io".into()).or_default();
+        if let Some(weight) = io.weight {
+            params.push(("weight".into(), format!("default {}", weight)));
+        }
+    }
+
+    if let Some(cpuset) = cgroups.cpuset.as_ref() {
+        let params = parameters.entry("cpuset".into()).or_default();
+        if let Some(cpus) = cpuset.cpus.as_ref() {
+            params.push(("cpus".into(), cpus.clone()));
+        }
+        if let Some(mems) = cpuset.mems.as_ref() {
+            params.push(("mems".into(), mems.clone()));
+        }
+    }
+
+    for (controller, params) in &cgroups.raw {
+        parameters
+            .entry(controller.clone())
+            .or_default()
+            .extend(params.iter().map(|(p, v)| translate(controller, p, v)));
+    }
+
+    if let Some(params) = parameters.get_mut("memory") {
+        match oom {
+            manifest::OomAction::Kill => (),
+            manifest::OomAction::Report => throttle(params),
+            manifest::OomAction::Stop | manifest::OomAction::Restart => {
+                throttle(params);
+                // Kill the whole group instead of a single process if a limit is hit anyway
+                params.push(("oom.group".into(), "1".into()));
+            }
+        }
+    }
+
+    parameters
+}
+
+/// Replace the memory.max limit in `params` with memory.high
+fn throttle(params: &mut [(String, String)]) {
+    for (param, _) in params.iter_mut().filter(|(param, _)| param == "max") {
+        *param = "high".into();
+    }
+}
+
+/// Conversion of the shares range [2, 262144] to the weight range [1, 10000]
+fn shares_to_weight(shares: u64) -> u64 {
+    let shares = shares.clamp(2, 262_144);
+    1 + ((shares - 2) * 9999) / 262_142
+}
+
+/// Translate raw cgroup v1 parameters that have a direct v2 equivalent. Manifests
+/// written for v1 keep working with the unified hierarchy.
+fn translate(controller: &str, param: &str, value: &str) -> (String, String) {
+    match (controller, param) {
+        ("memory", "limit_in_bytes") => {
+            let value = if value.trim() == "-1" { "max" } else { value };
+            ("max".into(), value.into())
+        }
+        ("cpu", "shares") => match value.trim().parse::<u64>() {
+            Ok(shares) => ("weight".into(), shares_to_weight(shares).to_string()),
+            Err(_) => (param.into(), value.into()),
+        },
+        _ => (param.into(), value.into()),
+    }
+}
+
+/// Watch memory.events of the group at `path` with inotify and report an oom
+/// condition in case. Unless `oom` is `OomAction::Kill` the group is throttled at
+/// memory.high and exceeding it is reported at most once per `HIGH_REPORT_INTERVAL`.
+fn memory_monitor(
+    container: Container,
+    path: &Path,
+    oom: manifest::OomAction,
+    tx: EventTx,
+    stop: CancellationToken,
+) -> Result<(), Error> {
+    let memory_events = path.join(MEMORY_EVENTS);
+
+    let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)
+        .map_err(|e| Error::Io("Failed to initialize inotify".into(), from_nix(e)))?;
+    let fd = Fd(inotify.as_raw_fd());
+    inotify
+        .add_watch(&memory_events, AddWatchFlags::IN_MODIFY)
+        .map_err(|e| {
+            Error::Io(
+                format!("Failed to watch {}", memory_events.display()),
+                from_nix(e),
+            )
+        })?;
+
+    // Report increments of the oom counter and of the high counter of throttled groups
+    let throttled = oom != manifest::OomAction::Kill;
+    let mut ooms = 0;
+    let mut highs = 0;
+    let mut reported: Option<Instant> = None;
+    let is_oom = move |events: &str| {
+        let count = counter(events, OOM);
+        let mut oom = count > ooms;
+        ooms = count;
+
+        let count = counter(events, MEMORY_HIGH);
+        if throttled && count > highs {
+            oom |= reported
+                .map(|reported| reported.elapsed() >= HIGH_REPORT_INTERVAL)
+                .unwrap_or(true);
+        }
+        highs = count;
+
+        if oom {
+            reported = Some(Instant::now());
+        }
+        oom
+    };
+
+    oom_monitor(container, fd, memory_events, is_oom, tx, stop)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{io, parameters, translate};
+    use crate::api::model::Io;
+    use npk::manifest::{
+        CGroups, CpuCGroup, CpusetCGroup, IoCGroup, MemoryCGroup, OomAction, PidsCGroup,
+    };
+
+    #[test]
+    fn typed() {
+        let cgroups = CGroups {
+            cpu: Some(CpuCGroup {
+                shares: Some(1024),
+                quota: Some(50000),
+                period: Some(200000),
+            }),
+            pids: Some(PidsCGroup { max: Some(10) }),
+            io: Some(IoCGroup { weight: Some(100) }),
+            cpuset: Some(CpusetCGroup {
+                cpus: Some("0-1".into()),
+                mems: None,
+            }),
+            ..Default::default()
+        };
+        let parameters = parameters(&cgroups, OomAction::Kill);
+        let params = |c: &str| {
+            parameters[c]
+                .iter()
+                .map(|(p, v)| (p.as_str(), v.as_str()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            params("cpu"),
+            vec![("weight", "39"), ("max", "50000 200000")]
+        );
+        assert_eq!(params("pids"), vec![("max", "10")]);
+        assert_eq!(params("io"), vec![("weight", "default 100")]);
+        assert_eq!(params("cpuset"), vec![("cpus", "0-1")]);
+        assert!(!parameters.contains_key("memory"));
+    }
+
+    #[test]
+    fn memory() {
+        let cgroups = CGroups {
+            memory: Some(MemoryCGroup { limit: Some(1000) }),
+            ..Default::default()
+        };
+        let params = |oom: OomAction| {
+            parameters(&cgroups, oom)["memory"]
+                .iter()
+                .map(|(p, v)| (p.to_string(), v.to_string()))
+                .collect::<Vec<_>>()
+        };
+        let p = |p: &str, v: &str| (p.to_string(), v.to_string());
+        assert_eq!(params(OomAction::Kill), vec![p("max", "1000")]);
+        assert_eq!(params(OomAction::Report), vec![p("high", "1000")]);
+        assert_eq!(
+            params(OomAction::Stop),
+            vec![p("high", "1000"), p("oom.group", "1")]
+        );
+        assert_eq!(
+            params(OomAction::Restart),
+            vec![p("high", "1000"), p("oom.group", "1")]
+        );
+    }
+
+    #[test]
+    fn io_stat() {
+        let content = "8:16 rbytes=1459200 wbytes=314773504 rios=192 wios=353 dbytes=0 dios=0
+8:0 rbytes=90430464 wbytes=299008000 rios=8950 wios=1252 dbytes=50331648 dios=3021
+";
+        assert_eq!(io(content), Io::new(91889664, 613781504));
+        assert_eq!(io(""), Io::new(0, 0));
+    }
+
+    #[test]
+    fn translate_v1() {
+        let t = translate;
+        assert_eq!(
+            t("memory", "limit_in_bytes", "1000"),
+            ("max".into(), "1000".into())
+        );
+        assert_eq!(
+            t("memory", "limit_in_bytes", "-1"),
+            ("max".into(), "max".into())
+        );
+        assert_eq!(t("memory", "max", "1000"), ("max".into(), "1000".into()));
+        assert_eq!(t("cpu", "shares", "2"), ("weight".into(), "1".into()));
+        assert_eq!(t("cpu", "shares", "1024"), ("weight".into(), "39".into()));
+        assert_eq!(
+            t("cpu", "shares", "262144"),
+            ("weight".into(), "10000".into())
+        );
+        assert_eq!(t("cpu", "weight", "100"), ("weight".into(), "100".into()));
+    }
+}
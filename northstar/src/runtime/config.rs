@@ -33,6 +33,18 @@ pub struct Config {
     pub cgroups: CGroups,
     pub devices: Devices,
 
+    /// Garbage collection of resource containers that are no longer used by a
+    /// started container. Defaults to never.
+    pub resource_gc: Option<ResourceGc>,
+
+    /// Time in milliseconds a updated container must run without exiting before the
+    /// update is completed. Defaults to 5000.
+    pub update_health_window: Option<u64>,
+
+    /// Additional seccomp profiles that can be referenced by manifests
+    #[serde(default)]
+    pub seccomp_profiles: HashMap<String, SeccompProfile>,
+
     /// Debugging options
     pub debug: Option<Debug>,
 }
@@ -43,13 +55,57 @@ pub struct Repository {
     pub dir: PathBuf,
     /// Directory where public signing keys are stored
     pub key: Option<PathBuf>,
+    /// Range of host uids containers of this repository may map in their user namespace.
+    /// User namespaces are not allowed if not set.
+    pub uid_range: Option<IdRange>,
+    /// Range of host gids containers of this repository may map in their user namespace.
+    /// User namespaces are not allowed if not set.
+    pub gid_range: Option<IdRange>,
+}
+
+/// Range of ids
+#[derive(Clone, Debug, Deserialize)]
+pub struct IdRange {
+    /// First id of the range
+    pub start: u32,
+    /// Length of the range
+    pub count: u32,
+}
+
+impl IdRange {
+    /// Return true if the `count` ids starting at `start` are within this range
+    pub fn contains(&self, start: u32, count: u32) -> bool {
+        let end = self.start as u64 + self.count as u64;
+        start >= self.start && start as u64 + count as u64 <= end
+    }
+}
+
+/// Policy for umounting resource containers once the last started container
+/// using them stopped or when they are mounted without a started container using them
+#[derive(Clone, Debug, Deserialize)]
+pub enum ResourceGc {
+    /// Umount immediately
+    #[serde(rename = "immediate")]
+    Immediate,
+    /// Umount after the given time in milliseconds unless a container using the
+    /// resource is started in the meantime
+    #[serde(rename = "delayed")]
+    Delayed(u64),
+    /// Keep unused resource containers mounted
+    #[serde(rename = "never")]
+    Never,
 }
 
 /// This map specifies the root cgroup under which the application cgroups are inserted.
 /// The directory is created if it does not exist.
 /// If not set for a specific cgroup, it defaults to "north".
+/// With the unified cgroup v2 hierarchy all controllers used by a container must
+/// be configured with the same directory.
 pub type CGroups = HashMap<String, PathBuf>;
 
+/// Seccomp profile mapping syscall names to argument rules
+pub type SeccompProfile = HashMap<String, String>;
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct Devices {
     /// Device mapper control file e.g /dev/mapper/control
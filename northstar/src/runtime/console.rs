@@ -12,7 +12,7 @@
 //   See the License for the specific language governing permissions and
 //   limitations under the License.
 
-use super::{Event, Notification, RepositoryId};
+use super::{Container, Event, Notification, RepositoryId};
 use crate::{
     api,
     runtime::{EventTx, ExitStatus},
@@ -26,7 +26,7 @@ use futures::{
     Future, StreamExt, TryFutureExt,
 };
 use log::{debug, error, info, trace, warn};
-use std::{fmt, path::PathBuf, unreachable};
+use std::{collections::HashMap, fmt, path::PathBuf, unreachable};
 use thiserror::Error;
 use tokio::{
     fs,
@@ -45,6 +45,14 @@ use url::Url;
 pub(crate) enum Request {
     Message(model::Message),
     Install(RepositoryId, mpsc::Receiver<Bytes>),
+    Update(Container, mpsc::Receiver<Bytes>),
+    Exec(
+        Container,
+        String,
+        Vec<String>,
+        HashMap<String, String>,
+        mpsc::Sender<model::ExecOutput>,
+    ),
 }
 
 /// A console is responsible for monitoring and serving incoming client connections
@@ -205,6 +213,9 @@ impl Console {
         };
         pin!(notifications);
 
+        // Output of commands executed on behalf of this connection
+        let (output_tx, mut output_rx) = mpsc::channel(100);
+
         loop {
             select! {
                 _ = stop.cancelled() => {
@@ -232,6 +243,14 @@ impl Console {
                         break;
                     }
                 }
+                output = output_rx.recv() => {
+                    // The connection keeps a sender. The channel is never closed.
+                    let output = output.expect("Exec output channel closed");
+                    if let Err(e) = network_stream.send(output).await {
+                        warn!("{}: Connection error: {}", peer, e);
+                        break;
+                    }
+                }
                 item = network_stream.next() => {
                     let message = if let Some(Ok(msg)) = item {
                         msg
@@ -240,7 +259,7 @@ impl Console {
                     };
 
                     trace!("{}: --> {:?}", peer, message);
-                    let response = match process_request(&peer, &mut network_stream, &stop, &event_tx, message).await {
+                    let response = match process_request(&peer, &mut network_stream, &stop, &event_tx, &output_tx, message).await {
                         Ok(response) => response,
                         Err(e) => {
                             warn!("Failed to process request: {}", e);
@@ -275,6 +294,7 @@ async fn process_request<S>(
     stream: &mut S,
     stop: &CancellationToken,
     event_loop: &EventTx,
+    output: &mpsc::Sender<model::Message>,
     message: model::Message,
 ) -> Result<model::Message, Error>
 where
@@ -282,22 +302,70 @@ where
 {
     let message_id = message.id.clone();
     let (reply_tx, reply_rx) = oneshot::channel();
-    if let model::Payload::Request(model::Request::Install(repository, size)) = message.payload {
-        debug!(
-            "{}: Received installation request with size {}",
-            client_id,
-            bytesize::ByteSize::b(size)
-        );
-
-        info!("{}: Using repository \"{}\"", client_id, repository);
-
-        // Send a Receiver<Bytes> to the runtime and forward n bytes to this channel
-        let (tx, rx) = mpsc::channel(10);
-        let request = Request::Install(repository, rx);
-        trace!("    {:?} -> event loop", request);
-        let event = Event::Console(request, reply_tx);
-        event_loop.send(event).map_err(|_| Error::Shutdown).await?;
 
+    // Installations and updates are followed by the npk. The runtime receives a
+    // Receiver<Bytes> and n bytes are forwarded to this channel.
+    let (request, npk) = match message.payload {
+        model::Payload::Request(model::Request::Install(repository, size)) => {
+            debug!(
+                "{}: Received installation request with size {}",
+                client_id,
+                bytesize::ByteSize::b(size)
+            );
+
+            info!("{}: Using repository \"{}\"", client_id, repository);
+
+            let (tx, rx) = mpsc::channel(10);
+            (Request::Install(repository, rx), Some((tx, size)))
+        }
+        model::Payload::Request(model::Request::Update(container, size)) => {
+            debug!(
+                "{}: Received update request of {} with size {}",
+                client_id,
+                container,
+                bytesize::ByteSize::b(size)
+            );
+
+            let (tx, rx) = mpsc::channel(10);
+            (Request::Update(container, rx), Some((tx, size)))
+        }
+        model::Payload::Request(model::Request::Exec(container, cmd, args, env)) => {
+            debug!(
+                "{}: Received exec request of {} in {}",
+                client_id, cmd, container
+            );
+
+            // Forward the output of the command tagged with the id of the request
+            let (tx, mut rx) = mpsc::channel(100);
+            let output = output.clone();
+            let id = message_id.clone();
+            task::spawn(async move {
+                while let Some(payload) = rx.recv().await {
+                    let message = model::Message {
+                        id: id.clone(),
+                        payload: model::Payload::Exec(payload),
+                    };
+                    if output.send(message).await.is_err() {
+                        break;
+                    }
+                }
+            });
+            (Request::Exec(container, cmd, args, env, tx), None)
+        }
+        payload => {
+            let message = model::Message {
+                id: message_id.clone(),
+                payload,
+            };
+            (Request::Message(message), None)
+        }
+    };
+
+    trace!("    {:?} -> event loop", request);
+    let event = Event::Console(request, reply_tx);
+    event_loop.send(event).map_err(|_| Error::Shutdown).await?;
+
+    if let Some((tx, size)) = npk {
         // If the connections breaks: just break. If the receiver is dropped: just break.
         let mut take = ReaderStream::new(BufReader::new(stream.take(size)));
         while let Some(Ok(buf)) = take.next().await {
@@ -305,11 +373,6 @@ where
                 break;
             }
         }
-    } else {
-        let request = Request::Message(message);
-        trace!("    {:?} -> event loop", request);
-        let event = Event::Console(request, reply_tx);
-        event_loop.send(event).map_err(|_| Error::Shutdown).await?;
     }
 
     (select! {
@@ -453,13 +516,24 @@ impl From<ExitStatus> for model::ExitStatus {
 impl From<Notification> for model::Notification {
     fn from(n: Notification) -> Self {
         match n {
-            Notification::OutOfMemory(container) => model::Notification::OutOfMemory(container),
+            Notification::OutOfMemory(container, action) => {
+                model::Notification::OutOfMemory(container, action)
+            }
             Notification::Exit { container, status } => model::Notification::Exit {
                 container,
                 status: status.into(),
             },
             Notification::Started(container) => model::Notification::Started(container),
+            Notification::StartFailed(container, error) => {
+                model::Notification::StartFailed(container, error)
+            }
             Notification::Stopped(container) => model::Notification::Stopped(container),
+            Notification::Paused(container) => model::Notification::Paused(container),
+            Notification::Resumed(container) => model::Notification::Resumed(container),
+            Notification::CrashLoop(container) => model::Notification::CrashLoop(container),
+            Notification::Update { from, to, phase } => {
+                model::Notification::Update { from, to, phase }
+            }
         }
     }
 }
@@ -182,6 +182,42 @@ impl Dm {
         Ok(())
     }
 
+    /// List the names of all DM devices
+    pub async fn list_devices(&self) -> Result<Vec<String>, Error> {
+        // struct dm_name_list { __u64 dev; __u32 next; char name[0]; }
+        const NEXT_OFFSET: usize = size_of::<u64>();
+        const NAME_OFFSET: usize = NEXT_OFFSET + size_of::<u32>();
+
+        let mut hdr = DmOptions::new().to_ioctl_hdr(None, DmFlags::empty());
+        let data = self
+            .do_ioctl(DM_LIST_DEVICES_CMD as u8, &mut hdr, None)
+            .await?;
+
+        let mut devices = Vec::new();
+        let mut entry = &data[..];
+        while entry.len() > NAME_OFFSET {
+            let mut dev = [0u8; NEXT_OFFSET];
+            dev.copy_from_slice(&entry[..NEXT_OFFSET]);
+            let mut next = [0u8; size_of::<u32>()];
+            next.copy_from_slice(&entry[NEXT_OFFSET..NAME_OFFSET]);
+            let next = u32::from_ne_bytes(next) as usize;
+
+            // An empty list is a single entry with dev 0
+            if u64::from_ne_bytes(dev) != 0 {
+                if let Some(name) = slice_to_null(&entry[NAME_OFFSET..]) {
+                    devices.push(String::from_utf8_lossy(name).into_owned());
+                }
+            }
+
+            if next == 0 || next > entry.len() {
+                break;
+            }
+            entry = &entry[next..];
+        }
+
+        Ok(devices)
+    }
+
     /// Create a DM device. It starts out in a "suspended" state.
     ///
     /// Valid flags: DM_READONLY, DM_PERSISTENT_DEV
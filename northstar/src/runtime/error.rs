@@ -37,18 +37,70 @@ pub enum Error {
     /// The container cannot be started because it's missing a running resource container
     #[error("Container {0} failed to start: Resource {1} is missing")]
     StartContainerMissingResource(Container, Container),
+    /// The container cannot be started because a dependency is not installed
+    #[error("Container {0} failed to start: Dependency {1} is missing")]
+    StartContainerMissingDependency(Container, Container),
     /// The container cannot be started
     #[error("Container {0} failed to start: {1}")]
     StartContainerFailed(Container, String),
     /// The container cannot be started because it's already running
     #[error("Container {0} failed to stop: Not started")]
     StopContainerNotStarted(Container),
+    /// The container cannot be paused because it's not running
+    #[error("Container {0} failed to pause: Not started")]
+    PauseContainerNotStarted(Container),
+    /// The container cannot be paused because it's already paused
+    #[error("Container {0} failed to pause: Already paused")]
+    PauseContainerPaused(Container),
+    /// The container cannot be paused because it has no freezer cgroup
+    #[error("Container {0} failed to pause: No freezer cgroup")]
+    PauseContainerUnsupported(Container),
+    /// The container cannot be resumed because it's not paused
+    #[error("Container {0} failed to resume: Not paused")]
+    ResumeContainerNotPaused(Container),
+    /// The container cannot be signaled because it's not running
+    #[error("Container {0} failed to kill: Not started")]
+    KillContainerNotStarted(Container),
+    /// The signal number is not a valid signal
+    #[error("Container {0} failed to kill: Invalid signal {1}")]
+    KillContainerInvalidSignal(Container, api::model::Signal),
+    /// The container cannot be mounted because a resource container is not installed
+    #[error("Container {0} failed to mount: Resource {1} is missing")]
+    MountContainerMissingResource(Container, Container),
+    /// The installed npk is not a different version of the updated container
+    #[error("Container {0} failed to update: {1} is not a different version")]
+    UpdateContainerMismatch(Container, Container),
+    /// The container is being updated or used by started containers
+    #[error("Container {0} failed to update: Busy")]
+    UpdateContainerBusy(Container),
+    /// The new version exited within the health window
+    #[error("Container {0} failed to update: Exited within the health window")]
+    UpdateContainerUnhealthy(Container),
+    /// The command cannot be executed because the container is not running
+    #[error("Container {0} failed to exec: Not started")]
+    ExecContainerNotStarted(Container),
+    /// The command cannot be executed because the container is paused
+    #[error("Container {0} failed to exec: Paused")]
+    ExecContainerPaused(Container),
+    /// The command, an argument or the environment contains a nul byte
+    #[error("Container {0} failed to exec: Invalid command: {1}")]
+    ExecContainerInvalidCommand(Container, String),
     /// The container is not known to the system
     #[error("Invalid repository {0}")]
     InvalidRepository(RepositoryId),
     /// The container is not known to the system
     #[error("Failed to install {0}: Already installed")]
     InstallDuplicate(Container),
+    /// The seccomp rules or the profile of the container are invalid
+    #[error("Failed to install {0}: Invalid seccomp configuration: {1}")]
+    InstallInvalidSeccomp(Container, String),
+    /// The dependencies of containers form a cycle
+    #[error("Dependency cycle: {}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(" -> "))]
+    DependencyCycle(Vec<Container>),
+
+    /// Multiple errors e.g of a failed start and its rollback
+    #[error("{}", .0.iter().map(ToString::to_string).collect::<Vec<_>>().join(", "))]
+    Multiple(Vec<Error>),
 
     #[error("NPK {0:?}: {1:?}")]
     Npk(String, npk::npk::Error),
@@ -75,6 +127,15 @@ impl Error {
     pub(crate) fn os<T: ToString>(e: T, err: nix::Error) -> Error {
         Error::Os(e.to_string(), err)
     }
+
+    /// Combine `errors` into one error. `errors` must not be empty.
+    pub(crate) fn multiple(mut errors: Vec<Error>) -> Error {
+        if errors.len() == 1 {
+            errors.remove(0)
+        } else {
+            Error::Multiple(errors)
+        }
+    }
 }
 
 impl From<Error> for api::model::Error {
@@ -92,16 +153,65 @@ impl From<Error> for api::model::Error {
             Error::StartContainerMissingResource(container, resource) => {
                 api::model::Error::StartContainerMissingResource(container, resource)
             }
+            Error::StartContainerMissingDependency(container, dependency) => {
+                api::model::Error::StartContainerMissingDependency(container, dependency)
+            }
             Error::StartContainerFailed(container, reason) => {
                 api::model::Error::StartContainerFailed(container, reason)
             }
             Error::StopContainerNotStarted(container) => {
                 api::model::Error::StopContainerNotStarted(container)
             }
+            Error::PauseContainerNotStarted(container) => {
+                api::model::Error::PauseContainerNotStarted(container)
+            }
+            Error::PauseContainerPaused(container) => {
+                api::model::Error::PauseContainerPaused(container)
+            }
+            Error::PauseContainerUnsupported(container) => {
+                api::model::Error::PauseContainerUnsupported(container)
+            }
+            Error::ResumeContainerNotPaused(container) => {
+                api::model::Error::ResumeContainerNotPaused(container)
+            }
+            Error::KillContainerNotStarted(container) => {
+                api::model::Error::KillContainerNotStarted(container)
+            }
+            Error::KillContainerInvalidSignal(container, signal) => {
+                api::model::Error::KillContainerInvalidSignal(container, signal)
+            }
+            Error::MountContainerMissingResource(container, resource) => {
+                api::model::Error::MountContainerMissingResource(container, resource)
+            }
+            Error::UpdateContainerMismatch(container, npk) => {
+                api::model::Error::UpdateContainerMismatch(container, npk)
+            }
+            Error::UpdateContainerBusy(container) => {
+                api::model::Error::UpdateContainerBusy(container)
+            }
+            Error::UpdateContainerUnhealthy(container) => {
+                api::model::Error::UpdateContainerUnhealthy(container)
+            }
+            Error::ExecContainerNotStarted(container) => {
+                api::model::Error::ExecContainerNotStarted(container)
+            }
+            Error::ExecContainerPaused(container) => {
+                api::model::Error::ExecContainerPaused(container)
+            }
+            Error::ExecContainerInvalidCommand(container, command) => {
+                api::model::Error::ExecContainerInvalidCommand(container, command)
+            }
             Error::InvalidRepository(repository) => {
                 api::model::Error::InvalidRepository(repository)
             }
             Error::InstallDuplicate(container) => api::model::Error::InstallDuplicate(container),
+            Error::InstallInvalidSeccomp(container, error) => {
+                api::model::Error::InstallInvalidSeccomp(container, error)
+            }
+            Error::DependencyCycle(containers) => api::model::Error::DependencyCycle(containers),
+            Error::Multiple(errors) => {
+                api::model::Error::Multiple(errors.into_iter().map(Into::into).collect())
+            }
             Error::Npk(cause, error) => api::model::Error::Npk(cause, error.to_string()),
             Error::Console(error) => api::model::Error::Console(error.to_string()),
             Error::Cgroups(error) => api::model::Error::Cgroups(error.to_string()),
@@ -22,7 +22,7 @@ use nix::{
     unistd,
     unistd::{chown, Gid, Uid},
 };
-use npk::manifest::{self, MountOption, MountOptions, Resource, Tmpfs};
+use npk::manifest::{self, Manifest, MountOption, MountOptions, Resource, Tmpfs};
 use std::path::{Path, PathBuf};
 use tempfile::TempDir;
 use tokio::{fs::symlink, task};
@@ -186,8 +186,7 @@ async fn persist(
     config: &Config,
     container: &Container,
 ) -> Result<Mount, Error> {
-    let uid = container.manifest.uid;
-    let gid = container.manifest.gid;
+    let (uid, gid) = host_ids(&container.manifest);
     let dir = config.data_dir.join(&container.manifest.name);
 
     if !dir.exists() {
@@ -296,9 +295,8 @@ async fn dev(root: &Path, container: &Container) -> (Dev, Mount, Mount) {
     let dir = task::block_in_place(|| TempDir::new().expect("Failed to create tempdir"));
     debug!("Creating devfs in {}", dir.path().display());
 
-    task::block_in_place(|| {
-        dev_devices(dir.path(), container.manifest.uid, container.manifest.gid)
-    });
+    let (uid, gid) = host_ids(&container.manifest);
+    task::block_in_place(|| dev_devices(dir.path(), uid, gid));
     dev_symlinks(dir.path()).await;
 
     let source = dir.path().to_path_buf();
@@ -311,6 +309,17 @@ async fn dev(root: &Path, container: &Container) -> (Dev, Mount, Mount) {
     (Some(dir), mount, remount)
 }
 
+/// Uid and gid of the container process outside of its user namespace
+fn host_ids(manifest: &Manifest) -> (u32, u32) {
+    match manifest.user_namespace.as_ref() {
+        Some(user_namespace) => (
+            user_namespace.map_uid(manifest.uid).unwrap_or(manifest.uid),
+            user_namespace.map_gid(manifest.gid).unwrap_or(manifest.gid),
+        ),
+        None => (manifest.uid, manifest.gid),
+    }
+}
+
 fn dev_devices(dir: &Path, uid: u32, gid: u32) {
     use nix::sys::stat::mknod;
 
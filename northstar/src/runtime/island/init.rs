@@ -13,8 +13,8 @@
 //   limitations under the License.
 
 use super::{
-    clone::clone, fs::Mount, io::Fd, seccomp::AllowList, Checkpoint, Container, PipeRead, Start,
-    SIGNAL_OFFSET,
+    clone::clone, fs::Mount, io::Fd, netns, seccomp::AllowList, Checkpoint, Container, PipeRead,
+    Start, SIGNAL_OFFSET,
 };
 use nix::{
     errno::Errno,
@@ -26,9 +26,15 @@ use nix::{
     },
     unistd::{self, Uid},
 };
+use npk::manifest::{Network, RLimitResource, RLimitValue};
 use sched::CloneFlags;
 use std::{
-    collections::HashSet, env, ffi::CString, io::Read, os::unix::prelude::RawFd, process::exit,
+    collections::{HashMap, HashSet},
+    env,
+    ffi::CString,
+    io::Read,
+    os::unix::prelude::RawFd,
+    process::exit,
 };
 use sys::wait::{waitpid, WaitStatus};
 
@@ -42,6 +48,7 @@ pub(super) fn init(
     mounts: &[Mount],
     fds: &[(RawFd, Fd)],
     groups: &[u32],
+    netns: Option<RawFd>,
     seccomp: Option<AllowList>,
     mut checkpoint: Checkpoint,
     tripwire: PipeRead,
@@ -71,6 +78,15 @@ pub(super) fn init(
         .canonicalize()
         .expect("Failed to canonicalize root");
 
+    // Network
+    network(manifest.network.as_ref(), netns);
+
+    // Hostname
+    if manifest.uts_namespace.unwrap_or_default() {
+        let hostname = manifest.hostname.as_ref().unwrap_or(&manifest.name);
+        unistd::sethostname(hostname).expect("Failed to set hostname");
+    }
+
     // Mount
     mount(&mounts);
 
@@ -80,6 +96,9 @@ pub(super) fn init(
     // Pwd
     env::set_current_dir("/").expect("Failed to set cwd to /");
 
+    // Resource limits. Set prior to the uid change which drops the capability to raise them.
+    set_rlimits(&manifest.rlimits);
+
     // UID / GID
     setid(manifest.uid, manifest.gid);
 
@@ -95,7 +114,61 @@ pub(super) fn init(
     // Close and dup fds
     file_descriptors(fds);
 
-    // Clone
+    spawn(init, argv, env, seccomp, tripwire)
+}
+
+/// Exec function. Joins the namespaces, chroot, credentials and capabilities of a
+/// started container and spawns `cmd` like `init` spawns the application.
+#[allow(clippy::too_many_arguments)]
+pub(super) fn exec(
+    container: &Container,
+    cmd: &CString,
+    argv: &[CString],
+    env: &[CString],
+    namespaces: &[(RawFd, CloneFlags)],
+    root: RawFd,
+    fds: &[(RawFd, Fd)],
+    groups: &[u32],
+    seccomp: Option<AllowList>,
+    mut checkpoint: Checkpoint,
+    tripwire: PipeRead,
+) -> ! {
+    // Sync with parent. The parent assigns the cgroups in the meantime.
+    checkpoint.wait(Start::Start);
+    checkpoint.send(Start::Started);
+    drop(checkpoint);
+
+    // Namespaces of the init process. The user namespace comes first.
+    for (fd, namespace) in namespaces {
+        sched::setns(*fd, *namespace).expect("Failed to join namespace");
+        unistd::close(*fd).expect("Failed to close namespace");
+    }
+
+    // Chroot into the root of the init process
+    unistd::fchdir(root).expect("Failed to change to root");
+    unistd::chroot(".").expect("Failed to chroot");
+    unistd::close(root).expect("Failed to close root");
+    env::set_current_dir("/").expect("Failed to set cwd to /");
+
+    let manifest = &container.manifest;
+    set_rlimits(&manifest.rlimits);
+    setid(manifest.uid, manifest.gid);
+    setgroups(groups);
+    set_no_new_privs(true);
+    drop_capabilities(manifest.capabilities.as_ref());
+    file_descriptors(fds);
+
+    spawn(cmd, argv, env, seccomp, tripwire)
+}
+
+/// Clone the process that execs `init` and exit with its exit status once it terminates
+fn spawn(
+    init: &CString,
+    argv: &[CString],
+    env: &[CString],
+    seccomp: Option<AllowList>,
+    tripwire: PipeRead,
+) -> ! {
     match clone(CloneFlags::empty(), Some(SIGCHLD as i32)) {
         Ok(result) => match result {
             unistd::ForkResult::Parent { child } => {
@@ -144,6 +217,21 @@ pub(super) fn init(
     }
 }
 
+/// Join the named network namespace `netns` or set the loopback interface of a new network
+/// namespace up
+fn network(network: Option<&Network>, netns: Option<RawFd>) {
+    match network {
+        Some(Network::None) => netns::loopback_up().expect("Failed to set loopback up"),
+        Some(Network::Namespace(_)) => {
+            let netns = netns.expect("Missing network namespace");
+            sched::setns(netns, CloneFlags::CLONE_NEWNET)
+                .expect("Failed to join network namespace");
+            unistd::close(netns).expect("Failed to close network namespace");
+        }
+        None | Some(Network::Host) => (),
+    }
+}
+
 /// Execute list of mount calls
 fn mount(mounts: &[Mount]) {
     for mount in mounts {
@@ -287,6 +375,43 @@ fn setid(uid: u32, gid: u32) {
     }
 }
 
+/// Set resource limits
+fn set_rlimits(rlimits: &HashMap<RLimitResource, RLimitValue>) {
+    for (resource, value) in rlimits {
+        let resource = match resource {
+            RLimitResource::As => libc::RLIMIT_AS,
+            RLimitResource::Core => libc::RLIMIT_CORE,
+            RLimitResource::Cpu => libc::RLIMIT_CPU,
+            RLimitResource::Data => libc::RLIMIT_DATA,
+            RLimitResource::Fsize => libc::RLIMIT_FSIZE,
+            RLimitResource::Locks => libc::RLIMIT_LOCKS,
+            RLimitResource::Memlock => libc::RLIMIT_MEMLOCK,
+            RLimitResource::Msgqueue => libc::RLIMIT_MSGQUEUE,
+            RLimitResource::Nice => libc::RLIMIT_NICE,
+            RLimitResource::Nofile => libc::RLIMIT_NOFILE,
+            RLimitResource::Nproc => libc::RLIMIT_NPROC,
+            RLimitResource::Rss => libc::RLIMIT_RSS,
+            RLimitResource::Rtprio => libc::RLIMIT_RTPRIO,
+            RLimitResource::Rttime => libc::RLIMIT_RTTIME,
+            RLimitResource::Sigpending => libc::RLIMIT_SIGPENDING,
+            RLimitResource::Stack => libc::RLIMIT_STACK,
+        };
+        let limit = |value: Option<u64>| {
+            value
+                .map(|value| value as libc::rlim_t)
+                .unwrap_or(libc::RLIM_INFINITY)
+        };
+        let rlimit = libc::rlimit {
+            rlim_cur: limit(value.soft),
+            rlim_max: limit(value.hard),
+        };
+        let result = unsafe { libc::setrlimit(resource, &rlimit) };
+        Errno::result(result)
+            .map(drop)
+            .expect("Failed to set rlimit");
+    }
+}
+
 /// Become a session group leader
 fn setsid() {
     unistd::setsid().expect("Failed to call setsid");
@@ -12,43 +12,54 @@
 //   See the License for the specific language governing permissions and
 //   limitations under the License.
 
-use self::fs::Dev;
+use self::{fs::Dev, io::Fd};
 use super::{
     config::Config,
     error::Error,
-    pipe::{self, pipe, PipeRead, PipeRecv, PipeSend, PipeWrite, RawFdExt},
+    pipe::{self, pipe, AsyncPipeRead, PipeRead, PipeRecv, PipeSend, PipeWrite, RawFdExt},
     state::{MountedContainer, Process},
     Event, EventTx, ExitStatus, Pid,
 };
+use crate::api::model::ExecOutput;
 use async_trait::async_trait;
-use futures::{Future, TryFutureExt};
+use futures::{future::join, Future, TryFutureExt};
 use log::{debug, info, warn};
 use nix::{
     errno::Errno,
-    libc::c_int,
+    fcntl::{self, OFlag},
+    libc::{self, c_int},
     sched,
-    sys::{self, signal::Signal},
+    sys::{self, signal::Signal, stat::Mode},
     unistd,
 };
-use npk::manifest::Manifest;
+use npk::manifest::{Manifest, Network, UserNamespace};
 use sched::CloneFlags;
 use serde::{Deserialize, Serialize};
 use std::{
+    collections::HashMap,
     convert::TryFrom,
-    ffi::{c_void, CString},
-    fmt,
-    os::unix::io::AsRawFd,
+    ffi::{c_void, CString, NulError},
+    fmt, iter,
+    os::unix::{
+        fs::MetadataExt,
+        io::{AsRawFd, RawFd},
+    },
     ptr::null,
     thread,
 };
 use task::block_in_place as block;
-use tokio::{sync::mpsc::error::TrySendError, task, time};
+use tokio::{
+    io::AsyncReadExt,
+    sync::mpsc::{self, error::TrySendError},
+    task, time,
+};
 use Signal::SIGCHLD;
 
 mod clone;
 mod fs;
 mod init;
 mod io;
+mod netns;
 mod seccomp;
 mod utils;
 
@@ -69,6 +80,8 @@ pub(super) struct Island {
     tripwire_read: PipeRead,
     /// Unused writing end of the tripwire pipe. Keep it in Island for a proper close on `shutdown`
     tripwire_write: PipeWrite,
+    /// References to the named network namespaces joined by containers
+    netns: netns::Refs,
 }
 
 pub(super) enum IslandProcess {
@@ -78,12 +91,14 @@ pub(super) enum IslandProcess {
         io: (Option<io::Log>, Option<io::Log>),
         checkpoint: Checkpoint,
         _dev: Dev,
+        netns: Option<netns::Netns>,
     },
     Started {
         pid: Pid,
         exit_status: Box<dyn Future<Output = Result<ExitStatus, Error>> + Unpin + Send + Sync>,
         io: (Option<io::Log>, Option<io::Log>),
         _dev: Dev,
+        netns: Option<netns::Netns>,
     },
     Stopped,
 }
@@ -111,16 +126,20 @@ impl Island {
         block(|| tripwire_read.set_cloexec(true))
             .map_err(|e| Error::io("Setting cloexec on tripwire fd", e))?;
 
+        // Remove stale named network namespaces
+        block(|| netns::remove_all(&config))?;
+
         Ok(Island {
             tx,
             config,
             tripwire_read,
             tripwire_write,
+            netns: netns::Refs::default(),
         })
     }
 
     pub async fn shutdown(self) -> Result<(), Error> {
-        Ok(())
+        block(|| netns::remove_all(&self.config))
     }
 
     pub async fn create(&self, container: &Container) -> Result<Box<dyn Process>, Error> {
@@ -132,7 +151,12 @@ impl Island {
         let tripwire = self.tripwire_read.clone();
         let (mounts, dev) = fs::prepare_mounts(&self.config, &container).await?;
         let groups = groups(manifest);
-        let seccomp = seccomp_filter(&container);
+        let seccomp = seccomp_filter(&self.config, &container)?;
+        let (network_flags, netns) = network(&self.config, container, &self.netns)?;
+        let (netns, netns_ref) = match netns {
+            Some((fd, netns)) => (Some(fd), Some(netns)),
+            None => (None, None),
+        };
 
         // Do not close child tripwire fd as it will be needed to detect if the runtime process died
         fds.retain(|(read_fd, _)| read_fd != &self.tripwire_read.as_raw_fd());
@@ -142,12 +166,24 @@ impl Island {
         debug!("{} env is {:?}", manifest.name, env);
 
         // Clone init
-        let flags = CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWNS;
+        let mut flags = CloneFlags::CLONE_NEWPID | CloneFlags::CLONE_NEWNS | network_flags;
+        if manifest.user_namespace.is_some() {
+            flags |= CloneFlags::CLONE_NEWUSER;
+        }
+        if manifest.uts_namespace.unwrap_or_default() {
+            flags |= CloneFlags::CLONE_NEWUTS;
+        }
+        if manifest.ipc_namespace.unwrap_or_default() {
+            flags |= CloneFlags::CLONE_NEWIPC;
+        }
 
         match clone::clone(flags, Some(SIGCHLD as c_int)) {
             Ok(result) => match result {
                 unistd::ForkResult::Parent { child } => {
                     block(|| drop(checkpoint_init));
+                    if let Some(netns) = netns {
+                        block(|| unistd::close(netns).ok());
+                    }
                     debug!("Created {} with pid {}", container.container, child);
 
                     // Close writing part of log forwards if any
@@ -160,6 +196,19 @@ impl Island {
                         log
                     });
                     let pid = child.as_raw() as Pid;
+
+                    // Map the ids of the user namespace. The child waits for the start
+                    // signal before it relies on the mapping.
+                    if let Some(user_namespace) = manifest.user_namespace.as_ref() {
+                        if let Err(e) = block(|| write_id_maps(pid, user_namespace)) {
+                            block(|| {
+                                sys::signal::kill(child, Signal::SIGKILL).ok();
+                                sys::wait::waitpid(child, None).ok();
+                            });
+                            return Err(e);
+                        }
+                    }
+
                     let exit_status = Box::new(wait(container, pid, self.tx.clone()));
 
                     Ok(Box::new(IslandProcess::Created {
@@ -168,6 +217,7 @@ impl Island {
                         io: (stdout, stderr),
                         checkpoint: checkpoint_runtime,
                         _dev: dev,
+                        netns: netns_ref,
                     }))
                 }
                 unistd::ForkResult::Child => {
@@ -181,6 +231,7 @@ impl Island {
                         &mounts,
                         &fds,
                         &groups,
+                        netns,
                         seccomp,
                         checkpoint_init,
                         tripwire,
@@ -190,6 +241,194 @@ impl Island {
             Err(e) => panic!("Fork error: {}", e),
         }
     }
+
+    /// Clone a process that executes `cmd` in the namespaces, chroot, credentials and
+    /// capabilities of the init process `pid` of the started `container`. The command
+    /// is spawned once the returned `Exec` is started.
+    pub async fn exec(
+        &self,
+        container: &Container,
+        pid: Pid,
+        cmd: &str,
+        args: &[String],
+        vars: &HashMap<String, String>,
+    ) -> Result<Exec, Error> {
+        let manifest = &container.manifest;
+        let (cmd, argv, env) = exec_argv(container, cmd, args, vars)?;
+        let groups = groups(manifest);
+        let seccomp = seccomp_filter(&self.config, container)?;
+        let (checkpoint_runtime, checkpoint_init) = checkpoints();
+        let tripwire = self.tripwire_read.clone();
+
+        // The output of the command is read from pipes. Stdin is /dev/null.
+        let (stdout, stdout_write) =
+            block(pipe).map_err(|e| Error::io("Failed to open pipe", e))?;
+        let (stderr, stderr_write) =
+            block(pipe).map_err(|e| Error::io("Failed to open pipe", e))?;
+
+        // All inherited fds are closed in the child. The fds opened below are closed
+        // explicitly.
+        let mut fds = block(|| std::fs::read_dir("/proc/self/fd"))
+            .map_err(|e| Error::io("Failed to read /proc/self/fd", e))?
+            .flatten()
+            .filter_map(|entry| entry.file_name().to_str().and_then(|fd| fd.parse().ok()))
+            .map(|fd: RawFd| (fd, Fd::Close))
+            .collect::<HashMap<_, _>>();
+        fds.remove(&self.tripwire_read.as_raw_fd());
+
+        let stdin = block(|| {
+            fcntl::open(
+                "/dev/null",
+                OFlag::O_RDONLY | OFlag::O_CLOEXEC,
+                Mode::empty(),
+            )
+        })
+        .map_err(|e| Error::os("Failed to open /dev/null", e))?;
+
+        let namespaces = block(|| namespaces(pid));
+        let root = format!("/proc/{}/root", pid);
+        let root = block(|| {
+            fcntl::open(
+                root.as_str(),
+                OFlag::O_RDONLY | OFlag::O_DIRECTORY | OFlag::O_CLOEXEC,
+                Mode::empty(),
+            )
+        })
+        .map_err(|e| Error::os(format!("Failed to open {}", root), e));
+        let (namespaces, root) = match (namespaces, root) {
+            (Ok(namespaces), Ok(root)) => (namespaces, root),
+            (namespaces, root) => {
+                let fds = namespaces.iter().flatten().map(|(fd, _)| *fd);
+                let fds = fds.chain(root.iter().copied()).chain(iter::once(stdin));
+                for fd in fds {
+                    unistd::close(fd).ok();
+                }
+                return Err(namespaces.and(root).unwrap_err());
+            }
+        };
+
+        for (fd, target) in &[
+            (stdin, libc::STDIN_FILENO),
+            (stdout_write.as_raw_fd(), libc::STDOUT_FILENO),
+            (stderr_write.as_raw_fd(), libc::STDERR_FILENO),
+        ] {
+            fds.remove(fd);
+            fds.insert(*target, Fd::Dup(*fd));
+        }
+        let fds = fds.drain().collect::<Vec<_>>();
+
+        debug!("{} exec is {:?} with env {:?}", manifest.name, argv, env);
+
+        match clone::clone(CloneFlags::empty(), Some(SIGCHLD as c_int)) {
+            Ok(result) => match result {
+                unistd::ForkResult::Parent { child } => {
+                    block(|| {
+                        drop(checkpoint_init);
+                        drop(stdout_write);
+                        drop(stderr_write);
+                        let fds = namespaces.iter().map(|(fd, _)| *fd);
+                        for fd in fds.chain(iter::once(root)).chain(iter::once(stdin)) {
+                            unistd::close(fd).ok();
+                        }
+                    });
+                    debug!("Created exec of {} with pid {}", container.container, child);
+
+                    Ok(Exec {
+                        pid: child.as_raw() as Pid,
+                        checkpoint: checkpoint_runtime,
+                        stdout,
+                        stderr,
+                    })
+                }
+                unistd::ForkResult::Child => {
+                    drop(checkpoint_runtime);
+
+                    init::exec(
+                        container,
+                        &cmd,
+                        &argv,
+                        &env,
+                        &namespaces,
+                        root,
+                        &fds,
+                        &groups,
+                        seccomp,
+                        checkpoint_init,
+                        tripwire,
+                    );
+                }
+            },
+            Err(e) => panic!("Fork error: {}", e),
+        }
+    }
+}
+
+/// Command cloned by `Island::exec`
+pub(super) struct Exec {
+    pid: Pid,
+    checkpoint: Checkpoint,
+    stdout: PipeRead,
+    stderr: PipeRead,
+}
+
+impl Exec {
+    pub fn pid(&self) -> Pid {
+        self.pid
+    }
+
+    /// Spawn the command. The output and finally the exit status of the command are
+    /// sent to `output`.
+    pub async fn start(mut self, output: mpsc::Sender<ExecOutput>) -> Result<(), Error> {
+        let stdout = AsyncPipeRead::try_from(self.stdout)
+            .map_err(|e| Error::io("Failed to get async handler from pipe reader", e))?;
+        let stderr = AsyncPipeRead::try_from(self.stderr)
+            .map_err(|e| Error::io("Failed to get async handler from pipe reader", e))?;
+
+        self.checkpoint.async_send(Start::Start).await;
+        self.checkpoint.async_wait(Start::Started).await;
+
+        let pid = self.pid;
+        task::spawn(async move {
+            join(
+                forward(stdout, ExecOutput::Stdout, output.clone()),
+                forward(stderr, ExecOutput::Stderr, output.clone()),
+            )
+            .await;
+            match task::spawn_blocking(move || exit_status(pid)).await {
+                Ok(status) => drop(output.send(ExecOutput::Exit(status.into())).await),
+                Err(e) => warn!("Failed to wait for exec {}: {}", pid, e),
+            }
+        });
+        Ok(())
+    }
+
+    /// Kill the command before it is started
+    pub async fn abort(self) {
+        let pid = self.pid;
+        block(|| {
+            sys::signal::kill(unistd::Pid::from_raw(pid as i32), Signal::SIGKILL).ok();
+            exit_status(pid);
+        });
+    }
+}
+
+/// Forward the data read from `pipe` to `output` until the pipe is closed
+async fn forward(
+    mut pipe: AsyncPipeRead,
+    message: fn(Vec<u8>) -> ExecOutput,
+    output: mpsc::Sender<ExecOutput>,
+) {
+    let mut buffer = [0u8; 4096];
+    loop {
+        match pipe.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                if output.send(message(buffer[..n].to_vec())).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
 }
 
 #[async_trait]
@@ -210,6 +449,7 @@ impl Process for IslandProcess {
                 exit_status,
                 io,
                 _dev,
+                netns,
                 mut checkpoint,
             } => {
                 checkpoint.async_send(Start::Start).await;
@@ -220,37 +460,60 @@ impl Process for IslandProcess {
                     exit_status,
                     io,
                     _dev,
+                    netns,
                 }))
             }
             _ => unreachable!(),
         }
     }
 
-    /// Send a SIGTERM to the application. If the application does not terminate with a timeout
+    /// Send `signal` to the process group of the application
+    async fn kill(&self, signal: Signal) -> Result<(), Error> {
+        let pid = self.pid().await;
+        debug!("Sending {} to {}", signal, pid);
+        let process_group = unistd::Pid::from_raw(-(pid as i32));
+        match sys::signal::kill(process_group, Some(signal)) {
+            Ok(_) => Ok(()),
+            // The process is exiting. The exit is reported by the wait task
+            Err(nix::Error::Sys(Errno::ESRCH)) => {
+                debug!("Process {} already exited", pid);
+                Ok(())
+            }
+            Err(e) => Err(Error::Os(
+                format!("Failed to send {} to {}", signal, pid),
+                e,
+            )),
+        }
+    }
+
+    /// Send `signal` to the application. If the application does not terminate with a timeout
     /// it is SIGKILLed.
     async fn stop(
         self: Box<Self>,
+        signal: Signal,
         timeout: time::Duration,
     ) -> Result<(Box<dyn Process>, ExitStatus), super::error::Error> {
-        let (pid, mut exit_status, io) = match *self {
+        // The network namespace reference is released once the process exited
+        let (pid, mut exit_status, io, _netns) = match *self {
             IslandProcess::Created {
                 pid,
                 exit_status,
                 io,
+                netns,
                 ..
-            } => (pid, exit_status, io),
+            } => (pid, exit_status, io, netns),
             IslandProcess::Started {
                 pid,
                 exit_status,
                 io,
-                _dev,
-            } => (pid, exit_status, io),
+                netns,
+                ..
+            } => (pid, exit_status, io, netns),
             IslandProcess::Stopped { .. } => unreachable!(),
         };
-        debug!("Trying to send SIGTERM to {}", pid);
+        debug!("Trying to send {} to {}", signal, pid);
         let process_group = unistd::Pid::from_raw(-(pid as i32));
-        let sigterm = Some(sys::signal::SIGTERM);
-        let exit_status = match sys::signal::kill(process_group, sigterm) {
+        let exit_status = match sys::signal::kill(process_group, Some(signal)) {
             Ok(_) => {
                 match time::timeout(timeout, &mut exit_status).await {
                     Err(_) => {
@@ -274,7 +537,10 @@ impl Process for IslandProcess {
                 let exit_status = exit_status.await?;
                 Ok(exit_status)
             }
-            Err(e) => Err(Error::Os(format!("Failed to SIGTERM {}", process_group), e)),
+            Err(e) => Err(Error::Os(
+                format!("Failed to send {} to {}", signal, process_group),
+                e,
+            )),
         }?;
 
         if let Some(io) = io.0 {
@@ -312,58 +578,7 @@ fn wait(
 ) -> impl Future<Output = Result<ExitStatus, Error>> {
     let container = container.container.clone();
     task::spawn_blocking(move || {
-        let pid = unistd::Pid::from_raw(pid as i32);
-        let status = loop {
-            match sys::wait::waitpid(Some(pid), None) {
-                // The process exited normally (as with exit() or returning from main) with the given exit code.
-                // This case matches the C macro WIFEXITED(status); the second field is WEXITSTATUS(status).
-                Ok(sys::wait::WaitStatus::Exited(pid, code)) => {
-                    // There is no way to make the "init" exit with a signal status. Use a defined
-                    // offset to get the original signal. This is the sad way everyone does it...
-                    if SIGNAL_OFFSET <= code {
-                        let signal =
-                            Signal::try_from(code - SIGNAL_OFFSET).expect("Invalid signal offset");
-                        debug!("Process {} exit status is signal {}", pid, signal);
-                        break ExitStatus::Signaled(signal);
-                    } else {
-                        debug!("Process {} exit code is {}", pid, code);
-                        break ExitStatus::Exit(code);
-                    }
-                }
-
-                // The process was killed by the given signal.
-                // The third field indicates whether the signal generated a core dump. This case matches the C macro WIFSIGNALED(status); the last two fields correspond to WTERMSIG(status) and WCOREDUMP(status).
-                Ok(sys::wait::WaitStatus::Signaled(pid, signal, _dump)) => {
-                    debug!("Process {} exit status is signal {}", pid, signal);
-                    break ExitStatus::Signaled(signal);
-                }
-
-                // The process is alive, but was stopped by the given signal.
-                // This is only reported if WaitPidFlag::WUNTRACED was passed. This case matches the C macro WIFSTOPPED(status); the second field is WSTOPSIG(status).
-                Ok(sys::wait::WaitStatus::Stopped(_pid, _signal)) => continue,
-
-                // The traced process was stopped by a PTRACE_EVENT_* event.
-                // See nix::sys::ptrace and ptrace(2) for more information. All currently-defined events use SIGTRAP as the signal; the third field is the PTRACE_EVENT_* value of the event.
-                #[cfg(any(target_os = "linux", target_os = "android"))]
-                Ok(sys::wait::WaitStatus::PtraceEvent(_pid, _signal, _)) => continue,
-
-                // The traced process was stopped by execution of a system call, and PTRACE_O_TRACESYSGOOD is in effect.
-                // See ptrace(2) for more information.
-                #[cfg(any(target_os = "linux", target_os = "android"))]
-                Ok(sys::wait::WaitStatus::PtraceSyscall(_pid)) => continue,
-
-                // The process was previously stopped but has resumed execution after receiving a SIGCONT signal.
-                // This is only reported if WaitPidFlag::WCONTINUED was passed. This case matches the C macro WIFCONTINUED(status).
-                Ok(sys::wait::WaitStatus::Continued(_pid)) => continue,
-
-                // There are currently no state changes to report in any awaited child process.
-                // This is only returned if WaitPidFlag::WNOHANG was used (otherwise wait() or waitpid() would block until there was something to report).
-                Ok(sys::wait::WaitStatus::StillAlive) => continue,
-                // Retry the waitpid call if waitpid fails with EINTR
-                Err(e) if e == nix::Error::Sys(Errno::EINTR) => continue,
-                Err(e) => panic!("Failed to waitpid on {}: {}", pid, e),
-            }
-        };
+        let status = exit_status(pid);
 
         // Send notification to main loop
         loop {
@@ -384,6 +599,62 @@ fn wait(
     })
 }
 
+/// Wait for `pid` to exit and decode its exit status. This blocks.
+fn exit_status(pid: Pid) -> ExitStatus {
+    let pid = unistd::Pid::from_raw(pid as i32);
+    loop {
+        match sys::wait::waitpid(Some(pid), None) {
+            // The process exited normally (as with exit() or returning from main) with the given exit code.
+            // This case matches the C macro WIFEXITED(status); the second field is WEXITSTATUS(status).
+            Ok(sys::wait::WaitStatus::Exited(pid, code)) => {
+                // There is no way to make the "init" exit with a signal status. Use a defined
+                // offset to get the original signal. This is the sad way everyone does it...
+                if SIGNAL_OFFSET <= code {
+                    let signal =
+                        Signal::try_from(code - SIGNAL_OFFSET).expect("Invalid signal offset");
+                    debug!("Process {} exit status is signal {}", pid, signal);
+                    break ExitStatus::Signaled(signal);
+                } else {
+                    debug!("Process {} exit code is {}", pid, code);
+                    break ExitStatus::Exit(code);
+                }
+            }
+
+            // The process was killed by the given signal.
+            // The third field indicates whether the signal generated a core dump. This case matches the C macro WIFSIGNALED(status); the last two fields correspond to WTERMSIG(status) and WCOREDUMP(status).
+            Ok(sys::wait::WaitStatus::Signaled(pid, signal, _dump)) => {
+                debug!("Process {} exit status is signal {}", pid, signal);
+                break ExitStatus::Signaled(signal);
+            }
+
+            // The process is alive, but was stopped by the given signal.
+            // This is only reported if WaitPidFlag::WUNTRACED was passed. This case matches the C macro WIFSTOPPED(status); the second field is WSTOPSIG(status).
+            Ok(sys::wait::WaitStatus::Stopped(_pid, _signal)) => continue,
+
+            // The traced process was stopped by a PTRACE_EVENT_* event.
+            // See nix::sys::ptrace and ptrace(2) for more information. All currently-defined events use SIGTRAP as the signal; the third field is the PTRACE_EVENT_* value of the event.
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Ok(sys::wait::WaitStatus::PtraceEvent(_pid, _signal, _)) => continue,
+
+            // The traced process was stopped by execution of a system call, and PTRACE_O_TRACESYSGOOD is in effect.
+            // See ptrace(2) for more information.
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            Ok(sys::wait::WaitStatus::PtraceSyscall(_pid)) => continue,
+
+            // The process was previously stopped but has resumed execution after receiving a SIGCONT signal.
+            // This is only reported if WaitPidFlag::WCONTINUED was passed. This case matches the C macro WIFCONTINUED(status).
+            Ok(sys::wait::WaitStatus::Continued(_pid)) => continue,
+
+            // There are currently no state changes to report in any awaited child process.
+            // This is only returned if WaitPidFlag::WNOHANG was used (otherwise wait() or waitpid() would block until there was something to report).
+            Ok(sys::wait::WaitStatus::StillAlive) => continue,
+            // Retry the waitpid call if waitpid fails with EINTR
+            Err(e) if e == nix::Error::Sys(Errno::EINTR) => continue,
+            Err(e) => panic!("Failed to waitpid on {}: {}", pid, e),
+        }
+    }
+}
+
 /// Construct the init and argv argument for the containers execve
 fn init_argv(manifest: &Manifest) -> (CString, Vec<CString>) {
     // A container without an init shall not be started
@@ -438,6 +709,84 @@ fn env(manifest: &Manifest) -> Vec<CString> {
     env
 }
 
+/// Construct the cmd, argv and env arguments for the execve of a command executed in
+/// `container`. The environment of the container is extended and overruled by `vars`.
+fn exec_argv(
+    container: &Container,
+    cmd: &str,
+    args: &[String],
+    vars: &HashMap<String, String>,
+) -> Result<(CString, Vec<CString>, Vec<CString>), Error> {
+    let invalid = |e: NulError| {
+        Error::ExecContainerInvalidCommand(container.container.clone(), e.to_string())
+    };
+
+    let cmd = CString::new(cmd).map_err(invalid)?;
+    let mut argv = Vec::with_capacity(1 + args.len());
+    argv.push(cmd.clone());
+    for arg in args {
+        argv.push(CString::new(arg.as_str()).map_err(invalid)?);
+    }
+
+    let mut env = env(&container.manifest)
+        .into_iter()
+        .filter(|var| {
+            let key = var.as_bytes().split(|b| *b == b'=').next();
+            !vars.keys().any(|k| Some(k.as_bytes()) == key)
+        })
+        .collect::<Vec<_>>();
+    for (key, value) in vars {
+        env.push(CString::new(format!("{}={}", key, value)).map_err(invalid)?);
+    }
+
+    Ok((cmd, argv, env))
+}
+
+/// Open the namespaces of `pid` that differ from the namespaces of the runtime. The
+/// user namespace is first because it owns the other namespaces.
+fn namespaces(pid: Pid) -> Result<Vec<(RawFd, CloneFlags)>, Error> {
+    let mut namespaces = Vec::new();
+    for (name, namespace) in &[
+        ("user", CloneFlags::CLONE_NEWUSER),
+        ("ipc", CloneFlags::CLONE_NEWIPC),
+        ("uts", CloneFlags::CLONE_NEWUTS),
+        ("net", CloneFlags::CLONE_NEWNET),
+        ("pid", CloneFlags::CLONE_NEWPID),
+        ("mnt", CloneFlags::CLONE_NEWNS),
+    ] {
+        let path = format!("/proc/{}/ns/{}", pid, name);
+        let own = format!("/proc/self/ns/{}", name);
+        let result = std::fs::metadata(&path)
+            .and_then(|ns| {
+                std::fs::metadata(&own).map(|own| (ns.dev(), ns.ino()) != (own.dev(), own.ino()))
+            })
+            .map_err(|e| Error::io(format!("Failed to stat {}", path), e))
+            .and_then(|differs| {
+                differs
+                    .then(|| {
+                        fcntl::open(
+                            path.as_str(),
+                            OFlag::O_RDONLY | OFlag::O_CLOEXEC,
+                            Mode::empty(),
+                        )
+                    })
+                    .transpose()
+                    .map_err(|e| Error::os(format!("Failed to open {}", path), e))
+            });
+        match result {
+            Ok(Some(fd)) => namespaces.push((fd, *namespace)),
+            Ok(None) => (),
+            Err(e) => {
+                for (fd, _) in namespaces {
+                    unistd::close(fd).ok();
+                }
+                return Err(e);
+            }
+        }
+    }
+    Ok(namespaces)
+}
+
 /// Generate a list of supplementary gids if the groups info can be retrieved. This
 /// must happen before the init `clone` because the group information cannot be gathered
 /// without `/etc` etc...
@@ -463,12 +812,82 @@ fn groups(manifest: &Manifest) -> Vec<u32> {
     }
 }
 
-fn seccomp_filter(container: &Container) -> Option<seccomp::AllowList> {
+/// Clone flags and the fd of the named network namespace to join for the network
+/// configuration of `manifest`
+fn network(
+    config: &Config,
+    container: &Container,
+    refs: &netns::Refs,
+) -> Result<(CloneFlags, Option<(RawFd, netns::Netns)>), Error> {
+    let manifest = &container.manifest;
+    match manifest.network.as_ref() {
+        None | Some(Network::Host) => Ok((CloneFlags::empty(), None)),
+        Some(Network::None) => Ok((CloneFlags::CLONE_NEWNET, None)),
+        // The child cannot join a network namespace owned by the initial user namespace
+        // after it is cloned into a new user namespace
+        Some(Network::Namespace(name)) if manifest.user_namespace.is_some() => {
+            Err(Error::StartContainerFailed(
+                container.container.clone(),
+                format!(
+                    "Network namespace {} cannot be joined from a user namespace",
+                    name
+                ),
+            ))
+        }
+        Some(Network::Namespace(name)) => block(|| netns::open(config, name, refs))
+            .map(|netns| (CloneFlags::empty(), Some(netns))),
+    }
+}
+
+/// Write the uid and gid maps of the user namespace of `pid`
+fn write_id_maps(pid: Pid, user_namespace: &UserNamespace) -> Result<(), Error> {
+    for (file, maps) in &[
+        ("uid_map", &user_namespace.uid_map),
+        ("gid_map", &user_namespace.gid_map),
+    ] {
+        let path = format!("/proc/{}/{}", pid, file);
+        // The map must be written with a single write
+        let map = maps
+            .iter()
+            .map(|map| format!("{}\n", map))
+            .collect::<String>();
+        std::fs::write(&path, map)
+            .map_err(|e| Error::io(format!("Failed to write {}", path), e))?;
+    }
+    Ok(())
+}
+
+/// Check that the seccomp configuration of `manifest` is valid with the seccomp profiles
+/// of the runtime configuration
+pub(super) fn check_seccomp(
+    config: &Config,
+    container: &super::Container,
+    manifest: &Manifest,
+) -> Result<(), Error> {
+    match manifest.seccomp.as_ref() {
+        Some(seccomp) => seccomp::seccomp_filter(seccomp, &config.seccomp_profiles)
+            .map(drop)
+            .map_err(|e| Error::InstallInvalidSeccomp(container.clone(), e.to_string())),
+        None => Ok(()),
+    }
+}
+
+fn seccomp_filter(
+    config: &Config,
+    container: &Container,
+) -> Result<Option<seccomp::AllowList>, Error> {
     container
         .manifest
         .seccomp
         .as_ref()
-        .map(|seccomp| seccomp::seccomp_filter(seccomp.iter()))
+        .map(|seccomp| seccomp::seccomp_filter(seccomp, &config.seccomp_profiles))
+        .transpose()
+        .map_err(|e| {
+            Error::StartContainerFailed(
+                container.container.clone(),
+                format!("Invalid seccomp configuration: {}", e),
+            )
+        })
 }
 
 #[derive(Debug, Serialize, Deserialize, PartialEq)]
@@ -0,0 +1,198 @@
+// Copyright (c) 2021 ESRLabs
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use super::super::{config::Config, error::Error};
+use log::{debug, warn};
+use nix::{
+    errno::Errno,
+    fcntl::OFlag,
+    libc::{self, c_char, c_short},
+    mount::{self, MntFlags, MsFlags},
+    sched::{self, CloneFlags},
+    sys::{
+        socket::{self, AddressFamily, SockFlag, SockType},
+        stat::Mode,
+    },
+    unistd,
+};
+use std::{
+    collections::HashMap,
+    fs,
+    os::unix::io::RawFd,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    thread,
+};
+
+/// Directory within the run dir where named network namespaces are bind mounted
+const NETNS_DIR: &str = "netns";
+
+/// Number of references to each named network namespace
+pub(super) type Refs = Arc<Mutex<HashMap<String, usize>>>;
+
+/// Reference to a named network namespace joined by a container. The namespace is
+/// removed once the last reference is dropped.
+#[derive(Debug)]
+pub(crate) struct Netns {
+    name: String,
+    path: PathBuf,
+    refs: Refs,
+}
+
+impl Drop for Netns {
+    fn drop(&mut self) {
+        let mut refs = self.refs.lock().expect("Failed to lock netns refs");
+        let unused = match refs.get_mut(&self.name) {
+            Some(count) => {
+                *count -= 1;
+                *count == 0
+            }
+            None => false,
+        };
+        if unused {
+            refs.remove(&self.name);
+            debug!("Removing unused network namespace {}", self.name);
+            if let Err(e) = remove(&self.path) {
+                warn!("Failed to remove network namespace {}: {}", self.name, e);
+            }
+        }
+    }
+}
+
+/// Return the directory of named network namespaces
+fn dir(config: &Config) -> PathBuf {
+    config.run_dir.join(NETNS_DIR)
+}
+
+/// Open the named network namespace `name` and take a reference in `refs`. The
+/// namespace is created with the loopback interface up if it does not exist. The
+/// returned fd is opened with O_CLOEXEC.
+pub(super) fn open(config: &Config, name: &str, refs: &Refs) -> Result<(RawFd, Netns), Error> {
+    let dir = dir(config);
+    let path = dir.join(name);
+
+    // Creation and removal are serialized by the lock
+    let mut counts = refs.lock().expect("Failed to lock netns refs");
+
+    if !path.exists() {
+        debug!("Creating network namespace {}", name);
+        fs::create_dir_all(&dir)
+            .map_err(|e| Error::io(format!("Failed to create {}", dir.display()), e))?;
+        create(&path)?;
+    }
+
+    let fd = nix::fcntl::open(&path, OFlag::O_RDONLY | OFlag::O_CLOEXEC, Mode::empty())
+        .map_err(|e| Error::os(format!("Failed to open {}", path.display()), e))?;
+
+    *counts.entry(name.to_string()).or_default() += 1;
+    let netns = Netns {
+        name: name.to_string(),
+        path,
+        refs: refs.clone(),
+    };
+    Ok((fd, netns))
+}
+
+/// Remove all named network namespaces
+pub(super) fn remove_all(config: &Config) -> Result<(), Error> {
+    let dir = dir(config);
+    if !dir.exists() {
+        return Ok(());
+    }
+
+    let entries = fs::read_dir(&dir)
+        .map_err(|e| Error::io(format!("Failed to read {}", dir.display()), e))?;
+    for entry in entries {
+        let path = entry
+            .map_err(|e| Error::io(format!("Failed to read {}", dir.display()), e))?
+            .path();
+        debug!("Removing network namespace {}", path.display());
+        remove(&path)?;
+    }
+    Ok(())
+}
+
+/// Umount the named network namespace at `path` and remove the mount point
+fn remove(path: &Path) -> Result<(), Error> {
+    if let Err(e) = mount::umount2(path, MntFlags::MNT_DETACH) {
+        warn!("Failed to umount {}: {}", path.display(), e);
+    }
+    fs::remove_file(path).map_err(|e| Error::io(format!("Failed to remove {}", path.display()), e))
+}
+
+/// Create a new network namespace and bind mount it to `path`. The namespace is
+/// created in a dedicated thread because unshare affects the calling thread.
+/// The thread terminates afterwards and the namespace is kept alive by the mount.
+fn create(path: &Path) -> Result<(), Error> {
+    fs::File::create(path)
+        .map_err(|e| Error::io(format!("Failed to create {}", path.display()), e))?;
+
+    let target = path.to_owned();
+    let result = thread::spawn(move || -> Result<(), Error> {
+        sched::unshare(CloneFlags::CLONE_NEWNET)
+            .map_err(|e| Error::os("Failed to unshare network namespace", e))?;
+        loopback_up().map_err(|e| Error::os("Failed to set loopback up", e))?;
+        mount::mount(
+            Some("/proc/thread-self/ns/net"),
+            &target,
+            None::<&str>,
+            MsFlags::MS_BIND,
+            None::<&str>,
+        )
+        .map_err(|e| Error::os(format!("Failed to mount {}", target.display()), e))
+    })
+    .join()
+    .expect("Failed to join network namespace thread");
+
+    if result.is_err() {
+        fs::remove_file(path).ok();
+    }
+    result
+}
+
+/// Set the loopback interface of the current network namespace up
+pub(super) fn loopback_up() -> nix::Result<()> {
+    /// Subset of struct ifreq from linux/if.h used for SIOCGIFFLAGS and SIOCSIFFLAGS
+    #[repr(C)]
+    struct IfReq {
+        name: [c_char; libc::IFNAMSIZ],
+        flags: c_short,
+        _pad: [u8; 22],
+    }
+
+    let socket = socket::socket(
+        AddressFamily::Inet,
+        SockType::Datagram,
+        SockFlag::SOCK_CLOEXEC,
+        None,
+    )?;
+
+    let mut request = IfReq {
+        name: [0; libc::IFNAMSIZ],
+        flags: 0,
+        _pad: [0; 22],
+    };
+    for (dst, src) in request.name.iter_mut().zip(b"lo") {
+        *dst = *src as c_char;
+    }
+
+    let result = unsafe { libc::ioctl(socket, libc::SIOCGIFFLAGS as _, &mut request) };
+    let result = Errno::result(result).and_then(|_| {
+        request.flags |= libc::IFF_UP as c_short;
+        let result = unsafe { libc::ioctl(socket, libc::SIOCSIFFLAGS as _, &request) };
+        Errno::result(result).map(drop)
+    });
+    unistd::close(socket).ok();
+    result
+}
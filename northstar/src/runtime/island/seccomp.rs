@@ -12,12 +12,17 @@
 //   See the License for the specific language governing permissions and
 //   limitations under the License.
 
+use crate::runtime::config::SeccompProfile;
 use bindings::{
-    seccomp_data, sock_filter, sock_fprog, BPF_ABS, BPF_JEQ, BPF_JMP, BPF_K, BPF_LD, BPF_MAXINSNS,
-    BPF_RET, BPF_W, SECCOMP_RET_ALLOW, SECCOMP_RET_KILL, SECCOMP_RET_LOG, SYSCALL_MAP,
+    seccomp_data, sock_filter, sock_fprog, BPF_ABS, BPF_JA, BPF_JEQ, BPF_JGE, BPF_JGT, BPF_JMP,
+    BPF_JSET, BPF_K, BPF_LD, BPF_MAXINSNS, BPF_RET, BPF_W, SECCOMP_RET_ALLOW, SECCOMP_RET_DATA,
+    SECCOMP_RET_ERRNO, SECCOMP_RET_KILL, SECCOMP_RET_KILL_PROCESS, SECCOMP_RET_KILL_THREAD,
+    SECCOMP_RET_LOG, SECCOMP_RET_TRAP, SYSCALL_MAP,
 };
 use log::warn;
-use nix::errno::Errno;
+use nix::{errno::Errno, libc};
+use npk::manifest::{Seccomp, SeccompAction};
+use std::{collections::HashMap, convert::TryFrom, str::FromStr};
 use thiserror::Error;
 
 #[allow(unused, non_snake_case, non_camel_case_types, non_upper_case_globals)]
@@ -28,6 +33,10 @@ mod bindings {
     /// SECCOMP_RET_LOG is implemented by Linux 4.14 but not present in all C libs
     #[cfg(all(target_arch = "aarch64", target_os = "linux", target_env = "gnu"))]
     pub const SECCOMP_RET_LOG: u32 = 2147221504;
+
+    /// SECCOMP_RET_KILL_PROCESS is implemented by Linux 4.14 but not present in all C libs
+    #[cfg(all(target_arch = "aarch64", target_os = "linux", target_env = "gnu"))]
+    pub const SECCOMP_RET_KILL_PROCESS: u32 = 2147483648;
 }
 
 #[cfg(all(target_arch = "aarch64"))]
@@ -39,18 +48,198 @@ const AUDIT_ARCH: u32 = bindings::AUDIT_ARCH_X86_64;
 /// Syscalls used by northstar after the seccomp rules are applied and before the actual execve is done.
 const REQUIRED_SYSCALLS: &[u32] = &[bindings::SYS_execve];
 
-/// Construct a whitelist syscall filter that is applied post clone.
+/// Construct a whitelist syscall filter that is applied post clone. The profile referenced
+/// by `seccomp` is looked up in the runtime configured `profiles` and the built in profiles.
 pub(super) fn seccomp_filter(
-    filter: impl Iterator<Item = (impl ToString, impl ToString)>,
-) -> AllowList {
+    seccomp: &Seccomp,
+    profiles: &HashMap<String, SeccompProfile>,
+) -> Result<AllowList, Error> {
+    let mut allow = HashMap::new();
+    if let Some(profile) = seccomp.profile.as_ref() {
+        if let Some(rules) = profiles.get(profile) {
+            allow.extend(
+                rules
+                    .iter()
+                    .map(|(name, rule)| (name.as_str(), rule.as_str())),
+            );
+        } else {
+            let rules =
+                builtin_profile(profile).ok_or_else(|| Error::UnknownProfile(profile.clone()))?;
+            // Built in profiles list the syscalls of all supported architectures
+            allow.extend(
+                rules
+                    .into_iter()
+                    .filter(|(name, _)| translate_syscall(name).is_some()),
+            );
+        }
+    }
+    // Manifest rules replace the profile rules
+    allow.extend(
+        seccomp
+            .allow
+            .iter()
+            .map(|(name, rule)| (name.as_str(), rule.as_str())),
+    );
+
     let mut builder = Builder::new();
-    for (name, _) in filter {
-        if let Err(e) = builder.allow_syscall_name(&name.to_string()) {
-            // Continue here as a missing syscall on the allow list does not lead to insecure behaviour
-            warn!("Failed to allow syscall {}: {}", &name.to_string(), e);
+    if let Some(action) = seccomp.default.as_ref() {
+        builder.default_action(action);
+    }
+    for (name, rule) in allow {
+        let rule = rule.parse::<Rule>()?;
+        match builder.allow_syscall_rule(name, &rule) {
+            Ok(_) => (),
+            // Manifests may list syscalls that do not exist on this architecture. Continue
+            // here as a missing syscall on the allow list does not lead to insecure behaviour.
+            Err(Error::UnknownSyscall(_)) => warn!("Skipping unknown syscall {}", name),
+            Err(e) => return Err(e),
         }
     }
-    builder.build()
+    Ok(builder.build())
+}
+
+/// Syscalls needed by almost every process
+const PROFILE_MINIMAL: &[(&str, &str)] = &[
+    ("arch_prctl", "1"),
+    ("brk", "1"),
+    ("clock_gettime", "1"),
+    ("clock_nanosleep", "1"),
+    ("close", "1"),
+    ("exit", "1"),
+    ("exit_group", "1"),
+    ("fstat", "1"),
+    ("futex", "1"),
+    ("getpid", "1"),
+    ("getrandom", "1"),
+    ("gettid", "1"),
+    ("madvise", "1"),
+    ("mmap", "1"),
+    ("mprotect", "1"),
+    ("munmap", "1"),
+    ("nanosleep", "1"),
+    ("prlimit64", "1"),
+    ("read", "1"),
+    ("restart_syscall", "1"),
+    ("rt_sigaction", "1"),
+    ("rt_sigprocmask", "1"),
+    ("rt_sigreturn", "1"),
+    ("sched_yield", "1"),
+    ("set_robust_list", "1"),
+    ("set_tid_address", "1"),
+    ("sigaltstack", "1"),
+    ("write", "1"),
+];
+
+/// Syscalls for file io, threads, processes and polling in addition to `PROFILE_MINIMAL`.
+/// Namespaces cannot be created, ioctl is limited to terminal requests, signals cannot
+/// be sent to other process groups and prctl is limited to harmless options.
+const PROFILE_DEFAULT: &[(&str, &str)] = &[
+    ("access", "1"),
+    ("chdir", "1"),
+    (
+        "clone",
+        "arg0 in ~CLONE_NEWCGROUP && arg0 in ~CLONE_NEWIPC && arg0 in ~CLONE_NEWNET && \
+         arg0 in ~CLONE_NEWNS && arg0 in ~CLONE_NEWPID && arg0 in ~CLONE_NEWUSER && \
+         arg0 in ~CLONE_NEWUTS",
+    ),
+    ("dup", "1"),
+    ("dup2", "1"),
+    ("dup3", "1"),
+    ("epoll_create1", "1"),
+    ("epoll_ctl", "1"),
+    ("epoll_pwait", "1"),
+    ("epoll_wait", "1"),
+    ("eventfd2", "1"),
+    ("execve", "1"),
+    ("faccessat", "1"),
+    ("fcntl", "1"),
+    ("fdatasync", "1"),
+    ("fstatfs", "1"),
+    ("fsync", "1"),
+    ("ftruncate", "1"),
+    ("getcwd", "1"),
+    ("getdents64", "1"),
+    ("getegid", "1"),
+    ("geteuid", "1"),
+    ("getgid", "1"),
+    ("getppid", "1"),
+    ("getrusage", "1"),
+    ("getuid", "1"),
+    (
+        "ioctl",
+        "arg1 == TCGETS || arg1 == TCSETS || arg1 == TCSETSW || arg1 == TCSETSF || \
+         arg1 == TIOCGWINSZ || arg1 == TIOCSWINSZ || arg1 == TIOCGPGRP || arg1 == FIONREAD || \
+         arg1 == FIONBIO",
+    ),
+    // Positive pids and the own process group
+    ("kill", "arg0 <= 0x7fffffff"),
+    ("lseek", "1"),
+    ("lstat", "1"),
+    ("mkdirat", "1"),
+    ("mremap", "1"),
+    ("newfstatat", "1"),
+    ("open", "1"),
+    ("openat", "1"),
+    ("pipe", "1"),
+    ("pipe2", "1"),
+    ("poll", "1"),
+    ("ppoll", "1"),
+    (
+        "prctl",
+        "arg0 == PR_GET_NAME || arg0 == PR_SET_NAME || arg0 == PR_GET_PDEATHSIG || \
+         arg0 == PR_SET_PDEATHSIG || arg0 == PR_GET_DUMPABLE || arg0 == PR_GET_NO_NEW_PRIVS || \
+         arg0 == PR_SET_NO_NEW_PRIVS || arg0 == PR_CAPBSET_READ",
+    ),
+    ("pread64", "1"),
+    ("pselect6", "1"),
+    ("pwrite64", "1"),
+    ("readlink", "1"),
+    ("readlinkat", "1"),
+    ("readv", "1"),
+    ("renameat", "1"),
+    ("sched_getaffinity", "1"),
+    ("select", "1"),
+    ("stat", "1"),
+    ("statx", "1"),
+    ("sysinfo", "1"),
+    ("tgkill", "1"),
+    ("timerfd_create", "1"),
+    ("timerfd_settime", "1"),
+    ("umask", "1"),
+    ("uname", "1"),
+    ("unlinkat", "1"),
+    ("wait4", "1"),
+    ("writev", "1"),
+];
+
+/// Syscalls for network clients in addition to `PROFILE_DEFAULT`
+const PROFILE_NETWORK_CLIENT: &[(&str, &str)] = &[
+    ("connect", "1"),
+    ("getpeername", "1"),
+    ("getsockname", "1"),
+    ("getsockopt", "1"),
+    ("recvfrom", "1"),
+    ("recvmsg", "1"),
+    ("sendmsg", "1"),
+    ("sendto", "1"),
+    ("setsockopt", "1"),
+    ("shutdown", "1"),
+    (
+        "socket",
+        "arg0 == AF_UNIX || arg0 == AF_INET || arg0 == AF_INET6",
+    ),
+    ("socketpair", "arg0 == AF_UNIX"),
+];
+
+/// Syscalls and rules of the built in profile `name`
+fn builtin_profile(name: &str) -> Option<Vec<(&'static str, &'static str)>> {
+    let profile = match name {
+        "minimal" => vec![PROFILE_MINIMAL],
+        "default" => vec![PROFILE_MINIMAL, PROFILE_DEFAULT],
+        "network-client" => vec![PROFILE_MINIMAL, PROFILE_DEFAULT, PROFILE_NETWORK_CLIENT],
+        _ => return None,
+    };
+    Some(profile.into_iter().flatten().copied().collect())
 }
 
 #[derive(Error, Debug)]
@@ -59,10 +248,218 @@ pub enum Error {
     InvalidArguments,
     #[error("Unknown system call {0}")]
     UnknownSyscall(String),
+    #[error("Invalid rule \"{0}\": {1}")]
+    InvalidRule(String, String),
+    #[error("Unknown profile {0}")]
+    UnknownProfile(String),
     #[error("OS error: {0}")]
     Os(nix::Error),
 }
 
+/// Argument rule of a syscall. The rule is either `1` which allows the syscall
+/// unconditionally or an expression on the syscall arguments:
+///
+/// ```text
+/// rule       := "1" | expression
+/// expression := conjunction ( "||" conjunction )*
+/// conjunction:= comparison ( "&&" comparison )*
+/// comparison := "arg" [0-5] operator value
+/// operator   := "==" | "!=" | "<" | "<=" | ">" | ">=" | "&" | "in"
+/// value      := term ( "|" term )*
+/// term       := [ "~" ] ( number | constant )
+/// ```
+///
+/// `&` matches if any bit of `value` is set in the argument and `in` matches if the
+/// argument has no bits set outside of `value`. Numbers are decimal, hexadecimal
+/// (`0x`) or octal (leading `0`). Constants are well known flags like `PROT_EXEC`
+/// or `AF_UNIX`. Example: `arg2 in ~PROT_EXEC || arg2 in ~PROT_WRITE`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rule {
+    /// Disjunction of conjunctions. Empty if the syscall is allowed unconditionally.
+    clauses: Vec<Vec<Comparison>>,
+}
+
+impl Rule {
+    /// Rule that allows a syscall regardless of its arguments
+    pub fn unconditional() -> Rule {
+        Rule {
+            clauses: Vec::new(),
+        }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |reason: &str| Error::InvalidRule(s.to_string(), reason.to_string());
+
+        if s.trim() == "1" {
+            return Ok(Rule::unconditional());
+        }
+
+        let clauses = s
+            .split("||")
+            .map(|conjunction| {
+                conjunction
+                    .split("&&")
+                    .map(|comparison| Comparison::parse(comparison.trim()).map_err(invalid))
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Rule { clauses })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Operator {
+    Equal,
+    NotEqual,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    MaskedSet,
+    In,
+}
+
+/// Comparison of a syscall argument with a value
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Comparison {
+    arg: u32,
+    operator: Operator,
+    value: u64,
+}
+
+impl Comparison {
+    fn parse(s: &str) -> Result<Comparison, &'static str> {
+        let s = s.strip_prefix("arg").ok_or("expected argument")?;
+        let arg = s
+            .get(..1)
+            .and_then(|arg| arg.parse::<u32>().ok())
+            .filter(|arg| *arg < 6)
+            .ok_or("invalid argument index")?;
+        let s = s[1..].trim_start();
+
+        // Order matters: Two character operators must be checked first
+        let (operator, value) = [
+            ("==", Operator::Equal),
+            ("!=", Operator::NotEqual),
+            ("<=", Operator::LessOrEqual),
+            (">=", Operator::GreaterOrEqual),
+            ("<", Operator::Less),
+            (">", Operator::Greater),
+            ("&", Operator::MaskedSet),
+            ("in ", Operator::In),
+        ]
+        .iter()
+        .find_map(|(token, operator)| s.strip_prefix(token).map(|value| (*operator, value)))
+        .ok_or("invalid operator")?;
+
+        let value = value
+            .split('|')
+            .try_fold(0u64, |value, term| -> Result<u64, &str> {
+                let term = term.trim();
+                let (negate, term) = match term.strip_prefix('~') {
+                    Some(term) => (true, term.trim_start()),
+                    None => (false, term),
+                };
+                let term = parse_number(term)
+                    .or_else(|| translate_constant(term))
+                    .ok_or("invalid value")?;
+                Ok(value | if negate { !term } else { term })
+            })?;
+
+        Ok(Comparison {
+            arg,
+            operator,
+            value,
+        })
+    }
+
+    /// Generate the code for this comparison. If the comparison does not match the code jumps
+    /// `following` instructions past its end. Otherwise the instruction directly after the
+    /// comparison is evaluated.
+    fn code(&self, following: usize) -> Result<Vec<sock_filter>, Error> {
+        // Arguments are 64 bit wide and are compared in two 32 bit halves. Both supported
+        // architectures are little endian.
+        let low = memoffset::offset_of!(seccomp_data, args) as u32 + 8 * self.arg;
+        let high = low + 4;
+        let value_low = self.value as u32;
+        let value_high = (self.value >> 32) as u32;
+        let fail =
+            |skip: usize| u8::try_from(skip + following).map_err(|_| Error::InvalidArguments);
+        let jump = |op: u32, k: u32, jt: u8, jf: u8| bpf_jump(BPF_JMP | op | BPF_K, k, jt, jf);
+
+        let code = match self.operator {
+            Operator::Equal => vec![
+                bpf_load(high),
+                jump(BPF_JEQ, value_high, 0, fail(2)?),
+                bpf_load(low),
+                jump(BPF_JEQ, value_low, 0, fail(0)?),
+            ],
+            Operator::NotEqual => vec![
+                bpf_load(high),
+                jump(BPF_JEQ, value_high, 0, 2),
+                bpf_load(low),
+                jump(BPF_JEQ, value_low, fail(0)?, 0),
+            ],
+            Operator::Less => vec![
+                bpf_load(high),
+                jump(BPF_JGE, value_high, 0, 3),
+                jump(BPF_JEQ, value_high, 0, fail(2)?),
+                bpf_load(low),
+                jump(BPF_JGE, value_low, fail(0)?, 0),
+            ],
+            Operator::LessOrEqual => vec![
+                bpf_load(high),
+                jump(BPF_JGE, value_high, 0, 3),
+                jump(BPF_JEQ, value_high, 0, fail(2)?),
+                bpf_load(low),
+                jump(BPF_JGT, value_low, fail(0)?, 0),
+            ],
+            Operator::Greater => vec![
+                bpf_load(high),
+                jump(BPF_JGT, value_high, 3, 0),
+                jump(BPF_JEQ, value_high, 0, fail(2)?),
+                bpf_load(low),
+                jump(BPF_JGT, value_low, 0, fail(0)?),
+            ],
+            Operator::GreaterOrEqual => vec![
+                bpf_load(high),
+                jump(BPF_JGT, value_high, 3, 0),
+                jump(BPF_JEQ, value_high, 0, fail(2)?),
+                bpf_load(low),
+                jump(BPF_JGE, value_low, 0, fail(0)?),
+            ],
+            Operator::MaskedSet => vec![
+                bpf_load(high),
+                jump(BPF_JSET, value_high, 2, 0),
+                bpf_load(low),
+                jump(BPF_JSET, value_low, 0, fail(0)?),
+            ],
+            Operator::In => vec![
+                bpf_load(high),
+                jump(BPF_JSET, !value_high, fail(2)?, 0),
+                bpf_load(low),
+                jump(BPF_JSET, !value_low, fail(0)?, 0),
+            ],
+        };
+        Ok(code)
+    }
+}
+
+/// Parse a decimal, hexadecimal or octal number
+fn parse_number(s: &str) -> Option<u64> {
+    if let Some(hex) = s.strip_prefix("0x") {
+        u64::from_str_radix(hex, 16).ok()
+    } else if s.len() > 1 && s.starts_with('0') {
+        u64::from_str_radix(&s[1..], 8).ok()
+    } else {
+        s.parse().ok()
+    }
+}
+
 // Read-only list of allowed syscalls. Methods do not cause memory allocations on the heap.
 #[derive(Debug, Default)]
 pub struct AllowList {
@@ -100,7 +497,7 @@ impl AllowList {
 
 pub struct Builder {
     allowlist: Vec<sock_filter>,
-    log_only: bool,
+    default_action: u32,
 }
 
 impl Builder {
@@ -111,7 +508,7 @@ impl Builder {
     pub fn new() -> Self {
         let mut builder = Builder {
             allowlist: Vec::new(),
-            log_only: false,
+            default_action: SECCOMP_RET_KILL,
         };
 
         // Load architecture into accumulator
@@ -130,10 +527,7 @@ impl Builder {
         builder.allowlist.push(bpf_ret(SECCOMP_RET_KILL));
 
         // Load system call number into accumulator for subsequent filtering
-        builder.allowlist.push(bpf_stmt(
-            BPF_LD | BPF_W | BPF_ABS,
-            memoffset::offset_of!(seccomp_data, nr) as u32,
-        ));
+        builder.allowlist.push(bpf_load_nr());
 
         // Add default allowlist for architecture
         for syscall in REQUIRED_SYSCALLS {
@@ -155,28 +549,69 @@ impl Builder {
         self
     }
 
+    /// Add syscall number to whitelist if its arguments match `rule`
+    pub fn allow_syscall_nr_rule(&mut self, nr: u32, rule: &Rule) -> Result<&mut Builder, Error> {
+        if rule.clauses.is_empty() {
+            return Ok(self.allow_syscall_nr(nr));
+        }
+
+        // Each conjunction returns 'allow' if all its comparisons match. Otherwise
+        // the next conjunction is evaluated.
+        let mut rules = Vec::new();
+        for conjunction in &rule.clauses {
+            let mut code = vec![bpf_ret(SECCOMP_RET_ALLOW)];
+            for comparison in conjunction.iter().rev() {
+                let mut comparison = comparison.code(code.len())?;
+                comparison.append(&mut code);
+                code = comparison;
+            }
+            rules.append(&mut code);
+        }
+        // No conjunction matched: Restore the syscall number for the subsequent checks
+        rules.push(bpf_load_nr());
+
+        // If syscall matches evaluate the rules. If not, jump over the rules.
+        self.allowlist.push(bpf_jump(
+            BPF_JMP | BPF_JEQ | BPF_K,
+            nr,
+            Builder::SKIP_NEXT,
+            Builder::EVAL_NEXT,
+        ));
+        self.allowlist
+            .push(bpf_stmt(BPF_JMP | BPF_JA, rules.len() as u32));
+        self.allowlist.append(&mut rules);
+        Ok(self)
+    }
+
     /// Add syscall name to whitelist
+    #[allow(unused)]
     pub fn allow_syscall_name(&mut self, name: &str) -> Result<&mut Builder, Error> {
+        self.allow_syscall_rule(name, &Rule::unconditional())
+    }
+
+    /// Add syscall name to whitelist if its arguments match `rule`
+    pub fn allow_syscall_rule(&mut self, name: &str, rule: &Rule) -> Result<&mut Builder, Error> {
         match translate_syscall(name) {
-            Some(nr) => Ok(self.allow_syscall_nr(nr)),
+            Some(nr) => self.allow_syscall_nr_rule(nr, rule),
             None => Err(Error::UnknownSyscall(name.into())),
         }
     }
 
-    /// Log syscall violations only
-    #[allow(unused)]
-    pub fn log_only(&mut self) -> &mut Builder {
-        self.log_only = true;
+    /// Set the action taken on syscalls that are not allowed
+    pub fn default_action(&mut self, action: &SeccompAction) -> &mut Builder {
+        self.default_action = match action {
+            SeccompAction::KillProcess => SECCOMP_RET_KILL_PROCESS,
+            SeccompAction::KillThread => SECCOMP_RET_KILL_THREAD,
+            SeccompAction::Errno(errno) => SECCOMP_RET_ERRNO | (*errno as u32 & SECCOMP_RET_DATA),
+            SeccompAction::Trap => SECCOMP_RET_TRAP,
+            SeccompAction::Log => SECCOMP_RET_LOG,
+        };
         self
     }
 
     /// Apply seccomp rules
     pub fn build(mut self) -> AllowList {
-        if self.log_only {
-            self.allowlist.push(bpf_ret(SECCOMP_RET_LOG));
-        } else {
-            self.allowlist.push(bpf_ret(SECCOMP_RET_KILL));
-        }
+        self.allowlist.push(bpf_ret(self.default_action));
 
         AllowList {
             list: self.allowlist,
@@ -189,6 +624,88 @@ fn translate_syscall(name: &str) -> Option<u32> {
     SYSCALL_MAP.get(name).cloned()
 }
 
+/// Get value of a well known constant usable in syscall argument rules
+fn translate_constant(name: &str) -> Option<u64> {
+    macro_rules! constants {
+        ($($constant:ident),* $(,)?) => {
+            &[$((stringify!($constant), libc::$constant as u64)),*]
+        };
+    }
+
+    const CONSTANTS: &[(&str, u64)] = constants!(
+        AF_INET,
+        AF_INET6,
+        AF_NETLINK,
+        AF_PACKET,
+        AF_UNIX,
+        AF_UNSPEC,
+        CLONE_FILES,
+        CLONE_FS,
+        CLONE_NEWCGROUP,
+        CLONE_NEWIPC,
+        CLONE_NEWNET,
+        CLONE_NEWNS,
+        CLONE_NEWPID,
+        CLONE_NEWUSER,
+        CLONE_NEWUTS,
+        CLONE_SIGHAND,
+        CLONE_THREAD,
+        CLONE_VM,
+        F_DUPFD,
+        F_DUPFD_CLOEXEC,
+        F_GETFD,
+        F_GETFL,
+        F_SETFD,
+        F_SETFL,
+        FIONBIO,
+        FIONREAD,
+        MAP_ANONYMOUS,
+        MAP_FIXED,
+        MAP_PRIVATE,
+        MAP_SHARED,
+        O_APPEND,
+        O_CLOEXEC,
+        O_CREAT,
+        O_DIRECTORY,
+        O_EXCL,
+        O_NONBLOCK,
+        O_RDONLY,
+        O_RDWR,
+        O_TRUNC,
+        O_WRONLY,
+        PROT_EXEC,
+        PROT_NONE,
+        PROT_READ,
+        PROT_WRITE,
+        PR_CAPBSET_READ,
+        PR_GET_DUMPABLE,
+        PR_GET_NAME,
+        PR_GET_NO_NEW_PRIVS,
+        PR_GET_PDEATHSIG,
+        PR_SET_NAME,
+        PR_SET_NO_NEW_PRIVS,
+        PR_SET_PDEATHSIG,
+        SOCK_CLOEXEC,
+        SOCK_DGRAM,
+        SOCK_NONBLOCK,
+        SOCK_RAW,
+        SOCK_SEQPACKET,
+        SOCK_STREAM,
+        TCGETS,
+        TCSETS,
+        TCSETSF,
+        TCSETSW,
+        TIOCGPGRP,
+        TIOCGWINSZ,
+        TIOCSWINSZ,
+    );
+
+    CONSTANTS
+        .iter()
+        .find(|(constant, _)| *constant == name)
+        .map(|(_, value)| *value)
+}
+
 // https://git.kernel.org/pub/scm/linux/kernel/git/torvalds/linux.git/tree/include/uapi/linux/filter.h
 fn bpf_stmt(code: u32, k: u32) -> sock_filter {
     sock_filter {
@@ -211,3 +728,256 @@ fn bpf_jump(code: u32, k: u32, jt: u8, jf: u8) -> sock_filter {
 fn bpf_ret(k: u32) -> sock_filter {
     bpf_stmt(BPF_RET | BPF_K, k)
 }
+
+/// Load the 32 bit word at `offset` of `seccomp_data` into the accumulator
+fn bpf_load(offset: u32) -> sock_filter {
+    bpf_stmt(BPF_LD | BPF_W | BPF_ABS, offset)
+}
+
+/// Load the system call number into the accumulator
+fn bpf_load_nr() -> sock_filter {
+    bpf_load(memoffset::offset_of!(seccomp_data, nr) as u32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rule() {
+        assert_eq!("1".parse::<Rule>().unwrap(), Rule::unconditional());
+
+        let rule = "arg2 in ~PROT_EXEC || arg0 == 0x10 && arg1 & O_RDWR | 010"
+            .parse::<Rule>()
+            .unwrap();
+        assert_eq!(
+            rule.clauses,
+            vec![
+                vec![Comparison {
+                    arg: 2,
+                    operator: Operator::In,
+                    value: !(libc::PROT_EXEC as u64),
+                }],
+                vec![
+                    Comparison {
+                        arg: 0,
+                        operator: Operator::Equal,
+                        value: 16,
+                    },
+                    Comparison {
+                        arg: 1,
+                        operator: Operator::MaskedSet,
+                        value: libc::O_RDWR as u64 | 8,
+                    },
+                ],
+            ]
+        );
+
+        assert!("arg6 == 1".parse::<Rule>().is_err());
+        assert!("arg1 = 1".parse::<Rule>().is_err());
+        assert!("arg1 == UNKNOWN".parse::<Rule>().is_err());
+        assert!("".parse::<Rule>().is_err());
+    }
+
+    #[test]
+    fn profiles() {
+        let default = builtin_profile("default").unwrap();
+        assert!(PROFILE_MINIMAL.iter().all(|rule| default.contains(rule)));
+        assert!(builtin_profile("unknown").is_none());
+
+        let seccomp = Seccomp {
+            profile: Some("unknown".into()),
+            default: None,
+            allow: HashMap::new(),
+        };
+        assert!(seccomp_filter(&seccomp, &HashMap::new()).is_err());
+
+        let mut profiles = HashMap::new();
+        profiles.insert("unknown".to_string(), HashMap::new());
+        assert!(seccomp_filter(&seccomp, &profiles).is_ok());
+
+        // Invalid rules are rejected and unknown syscalls are skipped
+        let mut allow = HashMap::new();
+        allow.insert("unknown_syscall".to_string(), "1".to_string());
+        let seccomp = Seccomp {
+            profile: Some("minimal".into()),
+            default: None,
+            allow,
+        };
+        assert!(seccomp_filter(&seccomp, &HashMap::new()).is_ok());
+
+        let mut allow = HashMap::new();
+        allow.insert("ioctl".to_string(), "arg1 = TCGETS".to_string());
+        let seccomp = Seccomp {
+            profile: None,
+            default: None,
+            allow,
+        };
+        assert!(seccomp_filter(&seccomp, &HashMap::new()).is_err());
+    }
+
+    #[test]
+    fn default_profile() {
+        let seccomp = Seccomp {
+            profile: Some("default".into()),
+            default: None,
+            allow: HashMap::new(),
+        };
+        let program = seccomp_filter(&seccomp, &HashMap::new()).unwrap();
+        let allowed = |syscall: &str, args: &[u64]| {
+            let nr = translate_syscall(syscall).unwrap();
+            run(&program.list, nr, args) == SECCOMP_RET_ALLOW
+        };
+
+        let thread = (libc::CLONE_VM | libc::CLONE_THREAD | libc::CLONE_SIGHAND) as u64;
+        assert!(allowed("clone", &[thread]));
+        assert!(!allowed("clone", &[libc::CLONE_NEWUSER as u64]));
+        assert!(!allowed("clone", &[thread | libc::CLONE_NEWNET as u64]));
+
+        assert!(allowed("ioctl", &[0, libc::TCGETS as u64]));
+        assert!(allowed("ioctl", &[0, libc::TIOCGWINSZ as u64]));
+        assert!(!allowed("ioctl", &[0, libc::TIOCSTI as u64]));
+
+        assert!(allowed("kill", &[1, libc::SIGTERM as u64]));
+        assert!(allowed("kill", &[0, libc::SIGTERM as u64]));
+        assert!(!allowed("kill", &[-1i64 as u64, libc::SIGKILL as u64]));
+        assert!(!allowed(
+            "kill",
+            &[-2i32 as u32 as u64, libc::SIGKILL as u64]
+        ));
+
+        assert!(allowed("prctl", &[libc::PR_SET_NAME as u64]));
+        assert!(!allowed("prctl", &[libc::PR_SET_MM as u64]));
+    }
+
+    #[test]
+    fn rule_code() {
+        let mut builder = Builder::new();
+        let len = builder.allowlist.len();
+        let rule = "arg0 == 1 && arg1 > 2 || arg0 != 3"
+            .parse::<Rule>()
+            .unwrap();
+        builder.allow_syscall_nr_rule(1, &rule).unwrap();
+
+        // Syscall check, jump over rules, 4 + 5 + 1 + 4 + 1 instructions of rules, reload nr
+        assert_eq!(builder.allowlist.len() - len, 2 + 16);
+        assert_eq!(builder.allowlist[len + 1].k, 16);
+    }
+
+    /// Run the BPF `program` on the `seccomp_data` of syscall `nr` with `args` and return
+    /// the resulting action. Only the instructions generated by `Builder` are supported.
+    fn run(program: &[sock_filter], nr: u32, args: &[u64]) -> u32 {
+        let mut data = vec![0u8; std::mem::size_of::<seccomp_data>()];
+        let offset = memoffset::offset_of!(seccomp_data, nr);
+        data[offset..offset + 4].copy_from_slice(&nr.to_ne_bytes());
+        let offset = memoffset::offset_of!(seccomp_data, arch);
+        data[offset..offset + 4].copy_from_slice(&AUDIT_ARCH.to_ne_bytes());
+        for (n, arg) in args.iter().enumerate() {
+            let offset = memoffset::offset_of!(seccomp_data, args) + 8 * n;
+            data[offset..offset + 8].copy_from_slice(&arg.to_ne_bytes());
+        }
+
+        let mut accumulator = 0u32;
+        let mut pc = 0usize;
+        loop {
+            let instruction = &program[pc];
+            pc += 1;
+            let (jt, jf) = (instruction.jt as usize, instruction.jf as usize);
+            let k = instruction.k;
+            match instruction.code as u32 {
+                code if code == BPF_LD | BPF_W | BPF_ABS => {
+                    let offset = k as usize;
+                    let mut word = [0u8; 4];
+                    word.copy_from_slice(&data[offset..offset + 4]);
+                    accumulator = u32::from_ne_bytes(word);
+                }
+                code if code == BPF_RET | BPF_K => break k,
+                code if code == BPF_JMP | BPF_JA => pc += k as usize,
+                code if code == BPF_JMP | BPF_JEQ | BPF_K => {
+                    pc += if accumulator == k { jt } else { jf }
+                }
+                code if code == BPF_JMP | BPF_JGT | BPF_K => {
+                    pc += if accumulator > k { jt } else { jf }
+                }
+                code if code == BPF_JMP | BPF_JGE | BPF_K => {
+                    pc += if accumulator >= k { jt } else { jf }
+                }
+                code if code == BPF_JMP | BPF_JSET | BPF_K => {
+                    pc += if accumulator & k != 0 { jt } else { jf }
+                }
+                code => panic!("Unsupported instruction {:#x}", code),
+            }
+        }
+    }
+
+    /// Return true if `rule` allows the syscall with `args`
+    fn allowed(rule: &str, args: &[u64]) -> bool {
+        let nr = translate_syscall("ioctl").unwrap();
+        let mut builder = Builder::new();
+        builder
+            .allow_syscall_nr_rule(nr, &rule.parse::<Rule>().unwrap())
+            .unwrap();
+        let program = builder.build();
+        // Other syscalls are not affected by the rule
+        assert_eq!(run(&program.list, nr + 1, args), SECCOMP_RET_KILL);
+        run(&program.list, nr, args) == SECCOMP_RET_ALLOW
+    }
+
+    #[test]
+    fn rule_semantics() {
+        // The values differ in the low and in the high 32 bits
+        let cases: &[(&str, u64, bool)] = &[
+            ("arg0 == 0x100000005", 0x1_0000_0005, true),
+            ("arg0 == 0x100000005", 0x5, false),
+            ("arg0 == 0x100000005", 0x1_0000_0006, false),
+            ("arg0 == 0x100000005", 0x2_0000_0005, false),
+            ("arg0 != 0x100000005", 0x1_0000_0005, false),
+            ("arg0 != 0x100000005", 0x5, true),
+            ("arg0 != 0x100000005", 0x1_0000_0006, true),
+            ("arg0 < 0x100000005", 0x1_0000_0004, true),
+            ("arg0 < 0x100000005", 0xffff_ffff, true),
+            ("arg0 < 0x100000005", 0x1_0000_0005, false),
+            ("arg0 < 0x100000005", 0x1_0000_0006, false),
+            ("arg0 < 0x100000005", 0x2_0000_0000, false),
+            ("arg0 <= 0x100000005", 0x1_0000_0005, true),
+            ("arg0 <= 0x100000005", 0x6, true),
+            ("arg0 <= 0x100000005", 0x1_0000_0006, false),
+            ("arg0 <= 0x100000005", 0x2_0000_0000, false),
+            ("arg0 > 0x100000005", 0x1_0000_0006, true),
+            ("arg0 > 0x100000005", 0x2_0000_0000, true),
+            ("arg0 > 0x100000005", 0x1_0000_0005, false),
+            ("arg0 > 0x100000005", 0xffff_ffff, false),
+            ("arg0 >= 0x100000005", 0x1_0000_0005, true),
+            ("arg0 >= 0x100000005", 0x2_0000_0000, true),
+            ("arg0 >= 0x100000005", 0x1_0000_0004, false),
+            ("arg0 >= 0x100000005", 0x6, false),
+            ("arg0 & 0x100000004", 0x4, true),
+            ("arg0 & 0x100000004", 0x1_0000_0000, true),
+            ("arg0 & 0x100000004", 0x3, false),
+            ("arg0 & 0x100000004", 0x2_0000_0000, false),
+            ("arg0 in 0x100000004", 0x0, true),
+            ("arg0 in 0x100000004", 0x1_0000_0004, true),
+            ("arg0 in 0x100000004", 0x5, false),
+            ("arg0 in 0x100000004", 0x2_0000_0004, false),
+        ];
+        for (rule, arg, expected) in cases {
+            assert_eq!(
+                allowed(rule, &[*arg]),
+                *expected,
+                "{} with arg0 {:#x}",
+                rule,
+                arg
+            );
+        }
+
+        // Conjunctions and disjunctions on several arguments
+        let rule = "arg0 == 1 && arg1 > 2 || arg2 != 3";
+        assert!(allowed(rule, &[1, 3, 3]));
+        assert!(allowed(rule, &[0, 0, 4]));
+        assert!(!allowed(rule, &[1, 2, 3]));
+        assert!(!allowed(rule, &[0, 3, 3]));
+
+        // Unconditional rules
+        assert!(allowed("1", &[0x1_0000_0000]));
+    }
+}
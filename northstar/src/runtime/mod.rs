@@ -21,6 +21,7 @@ use nix::{
     sys::{signal, stat},
     unistd,
 };
+use npk::manifest::OomAction;
 use proc_mounts::MountIter;
 use repository::Repository;
 use state::State;
@@ -75,6 +76,14 @@ enum Event {
     Exit(Container, ExitStatus),
     /// Out of memory event occured
     Oom(Container),
+    /// The restart delay of a container expired
+    Restart(Container),
+    /// The dependencies of a container met their readiness conditions or timed out
+    Ready(Container),
+    /// The garbage collection delay of a unused resource container expired
+    ResourceGc(Container),
+    /// The health window of a updated container passed
+    UpdateHealthy(Container),
     /// Northstar shall shut down
     Shutdown,
     /// Notification events
@@ -91,13 +100,22 @@ enum ExitStatus {
 
 #[derive(new, Clone, Debug)]
 enum Notification {
-    OutOfMemory(Container),
+    OutOfMemory(Container, OomAction),
     Exit {
         container: Container,
         status: ExitStatus,
     },
     Started(Container),
+    StartFailed(Container, api::model::Error),
     Stopped(Container),
+    Paused(Container),
+    Resumed(Container),
+    CrashLoop(Container),
+    Update {
+        from: Container,
+        to: Container,
+        phase: api::model::UpdatePhase,
+    },
 }
 
 /// Result of a Runtime action
@@ -239,6 +257,9 @@ async fn runtime_task(config: &'_ Config, stop: CancellationToken) -> Result<(),
         None
     };
 
+    // Start all containers flagged with autostart
+    state.autostart().await;
+
     // Wait for a external shutdown request
     task::spawn(async move {
         stop.cancelled().await;
@@ -259,6 +280,14 @@ async fn runtime_task(config: &'_ Config, stop: CancellationToken) -> Result<(),
             Event::Oom(container) => state.on_oom(&container).await,
             // A container process existed. Check `process::wait_exit` for details.
             Event::Exit(container, exit_status) => state.on_exit(&container, &exit_status).await,
+            // The restart delay of a container with a restart policy expired.
+            Event::Restart(container) => state.on_restart(&container).await,
+            // The readiness wait of the dependencies of a container being started finished.
+            Event::Ready(container) => state.on_ready(&container).await,
+            // The umount delay of a unused resource container expired.
+            Event::ResourceGc(container) => state.on_resource_gc(&container).await,
+            // The health window of a updated container passed without exit.
+            Event::UpdateHealthy(container) => state.on_update_healthy(&container).await,
             // The runtime os commanded to shut down and exit.
             Event::Shutdown => {
                 debug!("Shutting down Northstar runtime");
@@ -19,16 +19,20 @@ use super::{
     key::PublicKey,
     loopdev::LoopControl,
     state::Npk,
+    Container,
 };
 use bitflags::_core::str::Utf8Error;
 use floating_duration::TimeAsFloat;
 use futures::{future::ready, Future, FutureExt};
-use log::{debug, info};
+use log::{debug, info, warn};
 pub use nix::mount::MsFlags as MountFlags;
+use nix::mount::{umount2, MntFlags};
 use npk::dm_verity::VerityHeader;
+use proc_mounts::MountIter;
 use std::{
+    convert::TryFrom,
     io,
-    os::unix::io::AsRawFd,
+    os::unix::{ffi::OsStrExt, io::AsRawFd},
     path::{Path, PathBuf},
     process,
     sync::Arc,
@@ -41,6 +45,8 @@ use tokio::{
 };
 
 const FS_TYPE: &str = "squashfs";
+/// Prefix of the names of device mapper devices created by the runtime
+const DM_NAME_PREFIX: &str = "northstar_";
 
 #[derive(Error, Debug)]
 pub enum Error {
@@ -71,6 +77,8 @@ pub(super) struct MountControl {
     dm: Arc<dm::Dm>,
     lc: Arc<LoopControl>,
     device_mapper_dev: String,
+    /// Prefix of the device mapper device names of this run dir
+    dm_prefix: String,
 }
 
 impl MountControl {
@@ -80,10 +88,22 @@ impl MountControl {
             .map_err(Error::LoopDevice)?;
         let dm = dm::Dm::new(&config.devices.device_mapper).map_err(Error::DeviceMapper)?;
         let device_mapper_dev = config.devices.device_mapper_dev.clone();
+
+        let run_dir = fs::canonicalize(&config.run_dir).await.map_err(|e| {
+            Error::Io(
+                format!("Failed to canonicalize {}", config.run_dir.display()),
+                e,
+            )
+        })?;
+        let dm_prefix = format!("{}{}_", DM_NAME_PREFIX, run_dir_id(&run_dir));
+
+        recover(&run_dir, &dm_prefix, &dm).await?;
+
         Ok(MountControl {
             lc: Arc::new(lc),
             dm: Arc::new(dm),
             device_mapper_dev,
+            dm_prefix,
         })
     }
 
@@ -99,13 +119,23 @@ impl MountControl {
         let lc = self.lc.clone();
         let target = target.to_owned();
         let device_mapper_dev = self.device_mapper_dev.clone();
+        let dm_prefix = self.dm_prefix.clone();
 
         task::spawn(async move {
             let start = time::Instant::now();
             let manifest = npk.manifest();
 
             debug!("Mounting {}:{}", manifest.name, manifest.version);
-            let device = attach(dm, lc, &device_mapper_dev, &npk, &target, key.is_some()).await?;
+            let device = attach(
+                dm,
+                lc,
+                &device_mapper_dev,
+                &dm_prefix,
+                &npk,
+                &target,
+                key.is_some(),
+            )
+            .await?;
             let duration = start.elapsed();
             info!(
                 "Mounted {}:{} Mounting: {:.03}s",
@@ -144,10 +174,88 @@ impl MountControl {
     }
 }
 
+/// Tear down the leftovers of a previous runtime instance that did not shut down
+/// cleanly: Container images still mounted in the canonical `run_dir` are umounted,
+/// their mount points removed and device mapper verity devices of other runtime
+/// processes with the same run dir are removed. The loop devices are released by the
+/// kernel once unused because they are set up with autoclear.
+async fn recover(run_dir: &Path, dm_prefix: &str, dm: &dm::Dm) -> Result<(), Error> {
+    let mounts = MountIter::new()
+        .map_err(|e| Error::Io("Failed to read mount info".into(), e))?
+        .filter_map(Result::ok)
+        .filter(|m| m.fstype == FS_TYPE && m.dest.parent() == Some(run_dir))
+        .map(|m| m.dest)
+        .collect::<Vec<_>>();
+    for target in mounts {
+        warn!("Umounting leftover mount {}", target.display());
+        if let Err(e) = task::block_in_place(|| umount2(&target, MntFlags::MNT_DETACH)) {
+            warn!("Failed to umount {}: {}", target.display(), e);
+        }
+    }
+
+    // Remove the mount points. Other entries like the network namespace directory
+    // are not empty and cannot be removed.
+    let mut entries = fs::read_dir(run_dir)
+        .await
+        .map_err(|e| Error::Io(format!("Failed to read {}", run_dir.display()), e))?;
+    while let Some(entry) = entries
+        .next_entry()
+        .await
+        .map_err(|e| Error::Io(format!("Failed to read {}", run_dir.display()), e))?
+    {
+        let path = entry.path();
+        let is_container = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| Container::try_from(name).is_ok())
+            .unwrap_or_default();
+        if is_container && path.is_dir() && fs::remove_dir(&path).await.is_ok() {
+            debug!("Removed leftover mount point {}", path.display());
+        }
+    }
+
+    for name in dm.list_devices().await.map_err(Error::DeviceMapper)? {
+        if is_leftover(&name, dm_prefix, process::id()) {
+            warn!("Removing leftover device mapper device {}", name);
+            let mut options = dm::DmOptions::new();
+            options.set_flags(dm::DmFlags::DM_DEFERRED_REMOVE);
+            if let Err(e) = dm.device_remove(&name, &options).await {
+                warn!("Failed to remove device mapper device {}: {:?}", name, e);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Id of `run_dir` used in the device mapper device names. Runtime instances with
+/// different run dirs do not touch the devices of each other.
+fn run_dir_id(run_dir: &Path) -> String {
+    // FNV-1a is used because the id must be stable across runtime versions
+    let hash = run_dir
+        .as_os_str()
+        .as_bytes()
+        .iter()
+        .fold(0x811c_9dc5u32, |hash, byte| {
+            (hash ^ *byte as u32).wrapping_mul(0x0100_0193)
+        });
+    format!("{:08x}", hash)
+}
+
+/// Return true if the device mapper device `name` was created for the run dir with
+/// `dm_prefix` by another process than `pid`. Device names are
+/// `<dm_prefix><pid>_<name>_<version>`.
+fn is_leftover(name: &str, dm_prefix: &str, pid: u32) -> bool {
+    name.strip_prefix(dm_prefix)
+        .map(|name| !name.starts_with(&format!("{}_", pid)))
+        .unwrap_or_default()
+}
+
 async fn attach(
     dm: Arc<dm::Dm>,
     lc: Arc<LoopControl>,
     device_mapper_dev: &str,
+    dm_prefix: &str,
     npk: &Arc<Npk>,
     target: &Path,
     verity: bool,
@@ -157,7 +265,8 @@ async fn attach(
     let fsimg_size = npk.fsimg_size();
     let manifest = npk.manifest();
     let dm_name = format!(
-        "northstar_{}_{}_{}",
+        "{}{}_{}_{}",
+        dm_prefix,
         process::id(),
         manifest.name,
         manifest.version
@@ -332,3 +441,49 @@ async fn wait_for_file_deleted(path: &Path, timeout: time::Duration) -> Result<(
         .map_err(|_| Error::Timeout(format!("Failed to wait for removal of {}", &path.display())))
         .and_then(|r| r)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{is_leftover, run_dir_id};
+    use std::path::Path;
+
+    #[test]
+    fn run_dir_ids() {
+        let id = run_dir_id(Path::new("/data/northstar/run"));
+        assert_eq!(id.len(), 8);
+        assert_eq!(id, run_dir_id(Path::new("/data/northstar/run")));
+        assert_ne!(id, run_dir_id(Path::new("/tmp/northstar/run")));
+    }
+
+    #[test]
+    fn leftovers() {
+        let prefix = "northstar_0123abcd_";
+        // Other process with the same run dir
+        assert!(is_leftover(
+            "northstar_0123abcd_100_hello_0.0.1",
+            prefix,
+            200
+        ));
+        // Own devices
+        assert!(!is_leftover(
+            "northstar_0123abcd_200_hello_0.0.1",
+            prefix,
+            200
+        ));
+        // Pid that starts with the own pid
+        assert!(is_leftover(
+            "northstar_0123abcd_2000_hello_0.0.1",
+            prefix,
+            200
+        ));
+        // Other run dir
+        assert!(!is_leftover(
+            "northstar_deadbeef_100_hello_0.0.1",
+            prefix,
+            200
+        ));
+        // Devices of earlier versions without a run dir id and of other tools
+        assert!(!is_leftover("northstar_100_hello_0.0.1", prefix, 200));
+        assert!(!is_leftover("cryptroot", prefix, 200));
+    }
+}
@@ -287,7 +287,7 @@ impl<T: AsRawFd> RawFdExt for T {
 }
 
 /// Maps an nix::Error to a io::Error
-fn from_nix(error: nix::Error) -> io::Error {
+pub(crate) fn from_nix(error: nix::Error) -> io::Error {
     match error {
         nix::Error::Sys(e) => io::Error::from_raw_os_error(e as i32),
         e => io::Error::new(io::ErrorKind::Other, e),
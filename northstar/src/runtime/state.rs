@@ -13,12 +13,18 @@
 //   limitations under the License.
 
 use super::{
-    cgroups, config::Config, console::Request, error::Error, island::Island, key::PublicKey,
-    mount::MountControl, repository::DirRepository, Container, Event, EventTx, ExitStatus,
-    Notification, Pid, Repository, RepositoryId,
+    cgroups,
+    config::{Config, ResourceGc},
+    console::Request,
+    error::Error,
+    island::{self, Island},
+    key::PublicKey,
+    mount::MountControl,
+    repository::DirRepository,
+    Container, Event, EventTx, ExitStatus, Notification, Pid, Repository, RepositoryId,
 };
 use crate::{api, runtime::repository::MemRepository};
-use api::model::Response;
+use api::model::{ExecOutput, MountResult, Response, UpdatePhase};
 use async_trait::async_trait;
 use bytes::Bytes;
 use floating_duration::TimeAsFloat;
@@ -27,23 +33,42 @@ use futures::{
     Future, FutureExt,
 };
 use log::{debug, error, info, warn};
-use npk::manifest::{Manifest, Mount, Resource};
+use nix::sys::signal::Signal;
+use npk::manifest::{
+    Manifest, Mount, OomAction, RLimitResource, RLimitValue, Resource, Restart, RestartPolicy,
+};
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{HashMap, HashSet, VecDeque},
+    convert::TryFrom,
     fmt::Debug,
     fs::File,
     io::BufReader,
+    iter,
     path::PathBuf,
     result,
+    str::FromStr,
     sync::Arc,
 };
 use tokio::{
+    fs, select,
     sync::{mpsc, oneshot},
     task, time,
 };
+use tokio_util::sync::CancellationToken;
 
 const INTERNAL_REPOSITORY: &str = "internal";
 
+/// Default delay before a container is restarted in milliseconds
+const DEFAULT_RESTART_BACKOFF: u64 = 500;
+/// Default upper limit of the restart delay in milliseconds
+const DEFAULT_RESTART_MAX_BACKOFF: u64 = 60_000;
+/// Default time a container has to exit after the stop signal in milliseconds
+const DEFAULT_STOP_TIMEOUT: u64 = 5_000;
+/// Default time a updated container must run before the update is completed in milliseconds
+const DEFAULT_UPDATE_HEALTH_WINDOW: u64 = 5_000;
+/// Default time to wait for a dependency to become ready in milliseconds
+const DEFAULT_READY_TIMEOUT: u64 = 5_000;
+
 type Repositories = HashMap<RepositoryId, Box<dyn Repository + Send + Sync>>;
 pub(super) type Npk = npk::npk::Npk<BufReader<File>>;
 
@@ -53,8 +78,10 @@ pub(super) trait Process: Send + Sync + Debug {
     async fn start(self: Box<Self>) -> Result<Box<dyn Process>, Error>;
     async fn stop(
         self: Box<Self>,
+        signal: Signal,
         timeout: time::Duration,
     ) -> Result<(Box<dyn Process>, ExitStatus), Error>;
+    async fn kill(&self, signal: Signal) -> Result<(), Error>;
     async fn destroy(self: Box<Self>) -> Result<(), Error>;
 }
 
@@ -64,10 +91,93 @@ pub(super) struct State<'a> {
     events_tx: EventTx,
     repositories: Repositories,
     containers: HashMap<Container, MountedContainer>,
+    restarts: HashMap<Container, Restarts>,
+    /// Number of started containers using a resource container
+    resource_refs: HashMap<Container, usize>,
+    /// Cancels the scheduled umount of unused resource containers
+    resource_gc: HashMap<Container, CancellationToken>,
+    /// Updates waiting for the new version to pass the health window
+    updates: HashMap<Container, PendingUpdate>,
+    /// Queued starts. Starts that touch the same containers are processed one after
+    /// the other.
+    starts: VecDeque<(Container, StartOrigin)>,
+    /// Starts waiting for dependencies to become ready
+    pending_starts: Vec<PendingStart>,
     mount_control: Arc<MountControl>,
     launcher_island: Island,
 }
 
+/// Restart bookkeeping of a container with a restart policy
+#[derive(Debug, Default)]
+struct Restarts {
+    /// Points in time of the restarts within the crash loop window
+    history: VecDeque<time::Instant>,
+    /// Cancels the scheduled restart
+    pending: Option<CancellationToken>,
+}
+
+/// Update of a started container waiting for the new version to pass the health window
+#[derive(Debug)]
+struct PendingUpdate {
+    /// Previous version that is restored if the new version exits. Uninstalled once
+    /// the new version passed the health window.
+    old: Container,
+    /// Cancels the health window timer
+    window: CancellationToken,
+}
+
+/// Origin of a start that determines how the result is reported
+#[derive(Debug)]
+enum StartOrigin {
+    /// Console request. The result is sent as response.
+    Console(oneshot::Sender<api::model::Response>),
+    /// Autostart and restarts. A failure is reported with a notification.
+    Runtime,
+    /// Start of the new version of a update of `old`
+    Update {
+        old: Container,
+        response_tx: oneshot::Sender<api::model::Response>,
+    },
+}
+
+/// Start of a container and its dependencies in progress
+#[derive(Debug)]
+struct PendingStart {
+    /// Container requested to be started
+    container: Container,
+    origin: StartOrigin,
+    /// Containers and resources touched by this start
+    touched: HashSet<Container>,
+    /// Containers left to start in start order
+    order: VecDeque<Container>,
+    /// Containers started by this start
+    started: Vec<Container>,
+    /// Containers mounted by this start
+    mounted: Vec<Container>,
+    /// Cancels the wait for the dependencies of the next container to become ready
+    wait: Option<CancellationToken>,
+}
+
+/// Readiness of the dependencies of a container
+#[derive(Debug)]
+enum Readiness {
+    Ready,
+    /// Conditions of dependencies that are not yet met
+    Wait(Vec<ReadyWait>),
+}
+
+/// Readiness conditions of a dependency that are not yet met
+#[derive(Debug)]
+struct ReadyWait {
+    dependency: Container,
+    /// End of the delay since the start of the dependency
+    until: Option<time::Instant>,
+    /// File that must exist
+    file: Option<PathBuf>,
+    /// Time to wait for the conditions
+    timeout: time::Duration,
+}
+
 #[derive(Debug)]
 pub(super) enum BlockDevice {
     Loopback(PathBuf),
@@ -89,13 +199,28 @@ pub(super) struct ProcessContext {
     started: time::Instant,
     debug: super::debug::Debug,
     cgroups: Option<cgroups::CGroups>,
+    /// The processes of the container are frozen
+    paused: bool,
 }
 
 impl ProcessContext {
-    async fn terminate(mut self, timeout: time::Duration) -> Result<ExitStatus, Error> {
+    async fn terminate(
+        mut self,
+        signal: Signal,
+        timeout: time::Duration,
+    ) -> Result<ExitStatus, Error> {
+        // Frozen processes cannot handle the termination signal
+        if self.paused {
+            if let Some(cgroups) = self.cgroups.as_ref() {
+                if let Err(e) = cgroups.thaw().await {
+                    warn!("Failed to thaw: {}", e);
+                }
+            }
+        }
+
         let (process, status) = self
             .process
-            .stop(timeout)
+            .stop(signal, timeout)
             .await
             .expect("Failed to terminate process");
 
@@ -176,10 +301,32 @@ impl<'a> State<'a> {
             .expect("Failed to start launcher");
         let mount_control = MountControl::new(&config).await.map_err(Error::Mount)?;
 
+        // Report processes of containers started by a previous runtime instance
+        if !config.cgroups.is_empty() {
+            match cgroups::orphans(&config.cgroups).await {
+                Ok(orphans) => {
+                    for (group, pids) in orphans {
+                        warn!(
+                            "Found orphaned container processes {:?} in {}",
+                            pids,
+                            group.display()
+                        );
+                    }
+                }
+                Err(e) => warn!("Failed to scan for orphaned container processes: {}", e),
+            }
+        }
+
         Ok(State {
             events_tx,
             repositories,
             containers: HashMap::new(),
+            restarts: HashMap::new(),
+            resource_refs: HashMap::new(),
+            resource_gc: HashMap::new(),
+            updates: HashMap::new(),
+            starts: VecDeque::new(),
+            pending_starts: Vec::new(),
             config,
             launcher_island,
             mount_control: Arc::new(mount_control),
@@ -240,6 +387,116 @@ impl<'a> State<'a> {
         Ok(task)
     }
 
+    /// Mount `containers` and the resources they use. The results are in the order of
+    /// `containers`. Containers that are already mounted are reported as mounted. Mounted
+    /// resources are subject to the resource garbage collection. A container is umounted again if one of its
+    /// resources fails to mount and the error of the resource is reported.
+    async fn mount_all(&mut self, containers: &[Container]) -> Vec<(Container, MountResult)> {
+        // Check the requested containers and find the containers to be mounted
+        let mut checked = Vec::new();
+        let mut need_mount = HashSet::new();
+        for container in containers {
+            if checked.iter().any(|(c, _)| c == container) {
+                continue;
+            }
+            let result = match self.npk(container) {
+                Some((npk, _)) => {
+                    let resources = resources(npk.manifest());
+                    if let Some(resource) = resources.iter().find(|r| self.npk(r).is_none()) {
+                        Err(Error::MountContainerMissingResource(
+                            container.clone(),
+                            resource.clone(),
+                        ))
+                    } else {
+                        need_mount.extend(
+                            iter::once(container)
+                                .chain(&resources)
+                                .filter(|c| !self.containers.contains_key(c))
+                                .cloned(),
+                        );
+                        Ok(resources)
+                    }
+                }
+                None => Err(Error::InvalidContainer(container.clone())),
+            };
+            checked.push((container.clone(), result));
+        }
+
+        // Mount
+        let mut mounts = Vec::new();
+        let mut failed = HashMap::new();
+        for container in &need_mount {
+            match self.mount(container).await {
+                Ok(mount) => mounts.push(mount.map(move |r| (container, r))),
+                Err(e) => {
+                    failed.insert(container.clone(), api::model::Error::from(e));
+                }
+            }
+        }
+        for (container, result) in join_all(mounts).await {
+            match result {
+                Ok(mounted_container) => {
+                    info!("Mounted {}", container);
+                    self.containers.insert(container.clone(), mounted_container);
+                }
+                Err(e) => {
+                    warn!("Failed to mount {}: {}", container, e);
+                    failed.insert(container.clone(), e.into());
+                }
+            }
+        }
+
+        // Collect the results of the requested containers
+        let mut results = HashMap::with_capacity(checked.len());
+        for (container, result) in checked {
+            let error = match result {
+                Ok(resources) => failed.get(&container).cloned().or_else(|| {
+                    resources
+                        .iter()
+                        .find_map(|resource| failed.get(resource))
+                        .cloned()
+                }),
+                Err(e) => Some(e.into()),
+            };
+            let result = match error {
+                Some(e) => {
+                    if need_mount.contains(&container) && self.containers.contains_key(&container) {
+                        warn!("Umounting {} because of failed resource mount", container);
+                        if let Err(e) = self.umount(&container).await {
+                            warn!("Failed to umount {}: {}", container, e);
+                        }
+                    }
+                    MountResult::Err(e)
+                }
+                None => MountResult::Ok,
+            };
+            results.insert(container, result);
+        }
+
+        // Resources mounted here are not used by a started container yet and are
+        // collected like the resources of a stopped container
+        let unused = need_mount
+            .iter()
+            .filter(|c| {
+                self.containers
+                    .get(c)
+                    .map(|c| c.manifest.init.is_none())
+                    .unwrap_or_default()
+            })
+            .filter(|c| !self.resource_refs.contains_key(c) && !self.resource_gc.contains_key(c))
+            .cloned()
+            .collect::<Vec<_>>();
+        for resource in &unused {
+            self.collect_resource(resource).await;
+        }
+
+        // One result per requested container. Duplicates share the result.
+        containers
+            .iter()
+            .map(|container| (container.clone(), results[container].clone()))
+            .collect()
+    }
+
     /// Umount a given container
     #[allow(clippy::blocks_in_if_conditions)]
     async fn umount(&mut self, container: &Container) -> Result<(), Error> {
@@ -291,13 +548,470 @@ impl<'a> State<'a> {
         Ok(())
     }
 
-    pub(super) async fn start(&mut self, container: &Container) -> Result<(), Error> {
+    /// Start `container` and its dependencies. Dependencies that are not yet started are
+    /// started in topological order. The readiness conditions of the dependencies are
+    /// awaited before a dependent container is started. The start is all or nothing:
+    /// if any container fails to start the containers started and mounted by this
+    /// attempt are stopped and umounted again. Starts are queued. A start that waits for
+    /// a dependency to become ready blocks only queued starts that touch the same
+    /// containers. The result is reported according to `origin`.
+    async fn start(&mut self, container: &Container, origin: StartOrigin) {
+        self.starts.push_back((container.clone(), origin));
+        self.process_starts().await;
+    }
+
+    /// Process the queued starts that touch no container of a pending start or of a
+    /// start queued before
+    async fn process_starts(&mut self) {
+        let mut index = 0;
+        while index < self.starts.len() {
+            let touched = self.touched(&self.starts[index].0);
+            let blocked = self
+                .pending_starts
+                .iter()
+                .map(|start| start.touched.clone())
+                .chain(self.starts.iter().take(index).map(|(c, _)| self.touched(c)))
+                .any(|other| !other.is_disjoint(&touched));
+            if blocked {
+                index += 1;
+                continue;
+            }
+            let (container, origin) = self.starts.remove(index).expect("Invalid index");
+
+            if self.is_started(&container) {
+                warn!("Application {} is already running", container);
+                let error = Error::StartContainerStarted(container.clone());
+                self.start_done(&container, origin, Err(error)).await;
+                continue;
+            }
+
+            let order = match self.start_order(&container, false) {
+                Ok(order) => order.into_iter().collect(),
+                Err(e) => {
+                    self.start_done(&container, origin, Err(e)).await;
+                    continue;
+                }
+            };
+
+            let start = PendingStart {
+                container,
+                origin,
+                touched,
+                order,
+                started: Vec::new(),
+                mounted: Vec::new(),
+                wait: None,
+            };
+            self.run_start(start, false).await;
+        }
+    }
+
+    /// Start the remaining containers of `start`. The start is suspended if the
+    /// dependencies of the next container are not ready. `waited` is set if the
+    /// readiness wait of the next container finished.
+    async fn run_start(&mut self, mut start: PendingStart, mut waited: bool) {
+        while let Some(c) = start.order.front().cloned() {
+            if c != start.container && self.is_started(&c) {
+                start.order.pop_front();
+                continue;
+            }
+            if c != start.container && !waited {
+                info!("Starting {} as dependency of {}", c, start.container);
+            }
+
+            let result = match self.readiness(&c).await {
+                Ok(Readiness::Ready) => {
+                    let before = self.containers.keys().cloned().collect::<HashSet<_>>();
+                    let result = self.start_container(&c).await;
+                    start.mounted.extend(
+                        self.containers
+                            .keys()
+                            .filter(|container| !before.contains(container))
+                            .cloned(),
+                    );
+                    result
+                }
+                Ok(Readiness::Wait(waits)) if !waited => {
+                    start.wait = Some(self.wait_ready(&c, waits));
+                    self.pending_starts.push(start);
+                    return;
+                }
+                Ok(Readiness::Wait(waits)) => {
+                    let dependency = &waits[0].dependency;
+                    warn!("Dependency {} of {} is not ready", dependency, c);
+                    Err(Error::StartContainerFailed(
+                        c.clone(),
+                        format!("Dependency {} is not ready", dependency),
+                    ))
+                }
+                Err(e) => Err(e),
+            };
+            waited = false;
+
+            match result {
+                Ok(_) => {
+                    start.order.pop_front();
+                    start.started.push(c);
+                }
+                Err(e) => {
+                    let error = self.rollback(e, &start.started, &start.mounted).await;
+                    self.start_done(&start.container, start.origin, Err(error))
+                        .await;
+                    return;
+                }
+            }
+        }
+        self.start_done(&start.container, start.origin, Ok(()))
+            .await;
+    }
+
+    /// Continue the pending start once the readiness wait for the dependencies of
+    /// `container` finished
+    pub(super) async fn on_ready(&mut self, container: &Container) -> Result<(), Error> {
+        let position = self
+            .pending_starts
+            .iter()
+            .position(|start| start.order.front() == Some(container));
+        if let Some(position) = position {
+            let start = self.pending_starts.remove(position);
+            self.run_start(start, true).await;
+            self.process_starts().await;
+        }
+        Ok(())
+    }
+
+    /// Report the result of a start of `container` according to `origin`
+    async fn start_done(
+        &mut self,
+        container: &Container,
+        origin: StartOrigin,
+        result: Result<(), Error>,
+    ) {
+        match origin {
+            StartOrigin::Console(response_tx) => {
+                let response = match result {
+                    Ok(_) => Response::Ok(()),
+                    Err(e) => {
+                        warn!("Failed to start {}: {}", container, e);
+                        Response::Err(e.into())
+                    }
+                };
+                // A error on the response_tx means that the connection
+                // was closed in the meantime. Ignore it.
+                response_tx.send(response).ok();
+            }
+            StartOrigin::Runtime => {
+                if let Err(e) = result {
+                    warn!("Failed to start {}: {}", container, e);
+                    self.notification(Notification::StartFailed(container.clone(), e.into()))
+                        .await;
+                }
+            }
+            StartOrigin::Update { old, response_tx } => {
+                self.update_started(&old, container, result, response_tx)
+                    .await
+            }
+        }
+    }
+
+    /// Undo a failed start: stop the containers in `started` in reverse order and umount
+    /// the containers in `mounted` that are still mounted. Errors of the rollback are returned
+    /// along with `error`. The result is always `Error::Multiple` if started containers
+    /// were stopped again to report that a partial start was undone.
+    async fn rollback(
+        &mut self,
+        error: Error,
+        started: &[Container],
+        mounted: &[Container],
+    ) -> Error {
+        let mut errors = vec![error];
+
+        for container in started.iter().rev() {
+            warn!("Stopping {} after failed start", container);
+            if let Err(e) = self.stop_container(container, None).await {
+                errors.push(e);
+            }
+        }
+
+        // Resources might have been collected when the started containers were stopped
+        let umount = mounted
+            .iter()
+            .rev()
+            .filter(|container| self.containers.contains_key(container))
+            .cloned()
+            .collect::<Vec<_>>();
+        for container in &umount {
+            warn!("Umounting {} after failed start", container);
+            if let Err(e) = self.umount(container).await {
+                errors.push(e);
+            }
+        }
+
+        if started.is_empty() {
+            Error::multiple(errors)
+        } else {
+            Error::Multiple(errors)
+        }
+    }
+
+    /// Return `container` and its transitive dependencies in start order: Dependencies
+    /// first. Missing dependencies are reported as error unless `ignore_missing` is set.
+    fn start_order(
+        &self,
+        container: &Container,
+        ignore_missing: bool,
+    ) -> Result<Vec<Container>, Error> {
+        fn visit(
+            state: &State,
+            container: &Container,
+            ignore_missing: bool,
+            path: &mut Vec<Container>,
+            order: &mut Vec<Container>,
+        ) -> Result<(), Error> {
+            if order.contains(container) {
+                return Ok(());
+            }
+
+            if let Some(position) = path.iter().position(|c| c == container) {
+                let mut cycle = path[position..].to_vec();
+                cycle.push(container.clone());
+                return Err(Error::DependencyCycle(cycle));
+            }
+
+            let (npk, _) = state
+                .npk(container)
+                .ok_or_else(|| Error::InvalidContainer(container.clone()))?;
+
+            path.push(container.clone());
+            for dependency in &npk.manifest().depends_on {
+                let dependency =
+                    Container::new(dependency.name.clone(), dependency.version.clone());
+                if state.npk(&dependency).is_none() {
+                    if ignore_missing {
+                        continue;
+                    }
+                    return Err(Error::StartContainerMissingDependency(
+                        container.clone(),
+                        dependency,
+                    ));
+                }
+                visit(state, &dependency, ignore_missing, path, order)?;
+            }
+            path.pop();
+
+            order.push(container.clone());
+            Ok(())
+        }
+
+        let mut order = Vec::new();
+        visit(self, container, ignore_missing, &mut Vec::new(), &mut order)?;
+        Ok(order)
+    }
+
+    /// Return `container`, its transitive dependencies and the resources used by them.
+    /// Starts that touch the same containers must not run concurrently.
+    fn touched(&self, container: &Container) -> HashSet<Container> {
+        let order = self
+            .start_order(container, true)
+            .unwrap_or_else(|_| vec![container.clone()]);
+        let resources = order
+            .iter()
+            .filter_map(|c| self.npk(c))
+            .flat_map(|(npk, _)| resources(npk.manifest()))
+            .collect::<Vec<_>>();
+        order.into_iter().chain(resources).collect()
+    }
+
+    /// Return all started containers in stop order: Dependents before their dependencies
+    fn stop_order(&self) -> Vec<Container> {
+        let mut started = self
+            .containers
+            .iter()
+            .filter(|(_, mounted_container)| mounted_container.process.is_some())
+            .map(|(container, _)| container.clone())
+            .collect::<Vec<_>>();
+        started.sort();
+
+        let mut order = Vec::with_capacity(started.len());
+        for container in &started {
+            let start_order = self
+                .start_order(container, true)
+                .unwrap_or_else(|_| vec![container.clone()]);
+            for c in start_order {
+                if !order.contains(&c) && started.contains(&c) {
+                    order.push(c);
+                }
+            }
+        }
+        order.reverse();
+        order
+    }
+
+    /// Return the started containers that depend directly or transitively on `container`
+    /// in stop order
+    fn dependents(&self, container: &Container) -> Vec<Container> {
+        self.stop_order()
+            .into_iter()
+            .filter(|c| c != container)
+            .filter(|c| {
+                self.start_order(c, true)
+                    .map(|order| order.contains(container))
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    /// Check the readiness conditions of the dependencies of `container`
+    async fn readiness(&self, container: &Container) -> Result<Readiness, Error> {
+        let (npk, _) = self
+            .npk(container)
+            .ok_or_else(|| Error::InvalidContainer(container.clone()))?;
+
+        let mut waits = Vec::new();
+        for dependency in &npk.manifest().depends_on {
+            let ready = match dependency.ready.as_ref() {
+                Some(ready) => ready,
+                None => continue,
+            };
+            let name = &dependency.name;
+            let dependency = Container::new(name.clone(), dependency.version.clone());
+            let started = self
+                .containers
+                .get(&dependency)
+                .and_then(|c| c.process.as_ref())
+                .map(|process| process.started)
+                .ok_or_else(|| {
+                    Error::StartContainerFailed(
+                        container.clone(),
+                        format!("Dependency {} is not started", dependency),
+                    )
+                })?;
+
+            let until = ready
+                .delay
+                .map(|delay| started + time::Duration::from_millis(delay))
+                .filter(|until| *until > time::Instant::now());
+            let file = match ready.file.as_ref() {
+                Some(file) => {
+                    let file = self.config.data_dir.join(name).join(file);
+                    match fs::metadata(&file).await {
+                        Ok(_) => None,
+                        Err(_) => Some(file),
+                    }
+                }
+                None => None,
+            };
+            let timeout = ready.timeout.unwrap_or(DEFAULT_READY_TIMEOUT);
+            let timeout = time::Duration::from_millis(timeout);
+
+            if until.is_some() || file.is_some() {
+                waits.push(ReadyWait {
+                    dependency,
+                    until,
+                    file,
+                    timeout,
+                });
+            }
+        }
+
+        if waits.is_empty() {
+            Ok(Readiness::Ready)
+        } else {
+            Ok(Readiness::Wait(waits))
+        }
+    }
+
+    /// Wait in the background until the conditions `waits` of the dependencies of
+    /// `container` are met or timed out. `Event::Ready` is sent once the wait finished.
+    fn wait_ready(&self, container: &Container, waits: Vec<ReadyWait>) -> CancellationToken {
+        for wait in &waits {
+            debug!("Waiting for {} to become ready", wait.dependency);
+        }
+
+        let token = CancellationToken::new();
+        let cancelled = token.clone();
+        let events_tx = self.events_tx.clone();
+        let container = container.clone();
+        task::spawn(async move {
+            let waits = waits.into_iter().map(|wait| {
+                time::timeout(wait.timeout, async move {
+                    if let Some(until) = wait.until {
+                        time::sleep_until(until).await;
+                    }
+                    if let Some(file) = wait.file {
+                        while fs::metadata(&file).await.is_err() {
+                            time::sleep(time::Duration::from_millis(10)).await;
+                        }
+                    }
+                })
+            });
+            select! {
+                _ = cancelled.cancelled() => (),
+                _ = join_all(waits) => {
+                    events_tx.send(Event::Ready(container)).await.ok();
+                }
+            }
+        });
+        token
+    }
+
+    /// Check that the user namespace id mappings of `manifest` are within the id ranges
+    /// configured for the repository of `container`
+    fn check_user_namespace(
+        &self,
+        container: &Container,
+        manifest: &Manifest,
+    ) -> Result<(), Error> {
+        let user_namespace = match manifest.user_namespace.as_ref() {
+            Some(user_namespace) => user_namespace,
+            None => return Ok(()),
+        };
+
+        let repository = self
+            .repositories
+            .iter()
+            .find(|(_, repository)| repository.get(container).is_some())
+            .and_then(|(id, _)| self.config.repositories.get(id))
+            .ok_or_else(|| Error::InvalidContainer(container.clone()))?;
+
+        for (maps, range, what) in &[
+            (&user_namespace.uid_map, &repository.uid_range, "uid"),
+            (&user_namespace.gid_map, &repository.gid_range, "gid"),
+        ] {
+            for map in maps.iter() {
+                if !range
+                    .as_ref()
+                    .map(|range| range.contains(map.outside, map.count))
+                    .unwrap_or_default()
+                {
+                    return Err(Error::StartContainerFailed(
+                        container.clone(),
+                        format!("The {} map {} is not allowed by the repository", what, map),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Return true if `container` is started
+    fn is_started(&self, container: &Container) -> bool {
+        self.containers
+            .get(container)
+            .map(|c| c.process.is_some())
+            .unwrap_or_default()
+    }
+
+    /// Mount and start a single container without considering its dependencies
+    async fn start_container(&mut self, container: &Container) -> Result<(), Error> {
         let start = time::Instant::now();
         info!("Trying to start {}", container);
 
         let mut need_mount = HashSet::new();
 
         if let Some((npk, _)) = self.npk(container) {
+            self.check_user_namespace(container, npk.manifest())?;
+
             // The the to be started container
             if let Some(mounted_container) = self.containers.get(container) {
                 // Check if the container is not a resource
@@ -315,19 +1029,10 @@ impl<'a> State<'a> {
                 need_mount.insert(container.clone());
             }
 
-            // Find to be mounted resources
-            for resource in npk
-                .manifest()
-                .mounts
-                .values()
-                .filter_map(|m| match m {
-                    Mount::Resource(Resource { name, version, .. }) => {
-                        Some(Container::new(name.clone(), version.clone()))
-                    }
-                    _ => None,
-                })
+            // Find to be mounted resources. Only not yet mounted ones
+            for resource in resources(npk.manifest())
+                .into_iter()
                 .filter(|resource| !self.containers.contains_key(resource))
-            // Only not yet mounted ones
             {
                 // Check if the resource is available
                 if self.npk(&resource).is_none() {
@@ -361,30 +1066,26 @@ impl<'a> State<'a> {
         // Mount :-)
         let mounts = join_all(mounts).await;
 
-        // Split into successful and failed ones
-        let (ok, mut failed): (Vec<_>, Vec<_>) =
-            mounts.into_iter().partition(|(_, result)| !result.is_err());
-
-        // Log mounts and insert into the list of mounted containers
-        for (container, mounted_container) in ok {
-            info!("Successfully mounted {}", container);
-            self.containers
-                .insert(container.clone(), mounted_container.unwrap());
-        }
-
-        // Log failures
-        for (container, err) in &failed {
-            warn!(
-                "Failed to mount {}: {}",
-                container,
-                err.as_ref().err().unwrap()
-            );
+        // Insert successful mounts into the list of mounted containers and collect
+        // the errors of the failed ones. Successful mounts are umounted by the caller
+        // if the start fails.
+        let mut errors = Vec::new();
+        for (container, result) in mounts {
+            match result {
+                Ok(mounted_container) => {
+                    info!("Successfully mounted {}", container);
+                    self.containers.insert(container.clone(), mounted_container);
+                }
+                Err(e) => {
+                    warn!("Failed to mount {}: {}", container, e);
+                    errors.push(e);
+                }
+            }
         }
 
         // At least one mount failed. Abort...
-        // TODO: All the errors should be returned
-        if let Some((_, Err(e))) = failed.pop() {
-            return Err(e);
+        if !errors.is_empty() {
+            return Err(Error::multiple(errors));
         }
 
         // This must exist
@@ -395,34 +1096,46 @@ impl<'a> State<'a> {
         let process = match self.launcher_island.create(&mounted_container).await {
             Ok(p) => p,
             Err(e) => {
-                warn!("Failed to create process for {}", container);
-                // Umount everything mounted so far for this start command
-                warn!("Failed to start {}", container);
+                warn!("Failed to create process for {}: {}", container, e);
                 return Err(e);
             }
         };
+        let pid = process.pid().await;
 
         // Debug
-        let debug = super::debug::Debug::new(
-            &self.config,
-            &mounted_container.manifest,
-            process.pid().await,
-        )
-        .await?;
-
-        // CGroups
-        let cgroups = if let Some(ref c) = mounted_container.manifest.cgroups {
+        let debug =
+            match super::debug::Debug::new(&self.config, &mounted_container.manifest, pid).await {
+                Ok(debug) => debug,
+                Err(e) => return Err(discard(e, Some(process), None, None).await),
+            };
+
+        // CGroups. Containers without cgroup configuration get a group if a freezer is
+        // configured in order to support pausing.
+        let freezer = self.config.cgroups.contains_key("freezer");
+        let cgroups = if mounted_container.manifest.cgroups.is_some() || freezer {
             debug!("Configuring CGroups of {}", container);
-            let cgroups =
-                // Creating a cgroup is a northstar internal thing. If it fails it's not recoverable.
-                cgroups::CGroups::new(&self.config.cgroups, &container, c, self.events_tx.clone())
-                    .await.expect("Failed to create cgroup");
-
-            // Assigning a pid to a cgroup created by us must work otherwise we did something wrong.
-            cgroups
-                .assign(process.pid().await)
-                .await
-                .expect("Failed to assign PID to cgroups");
+            let c = mounted_container
+                .manifest
+                .cgroups
+                .clone()
+                .unwrap_or_default();
+            let oom = mounted_container.manifest.oom.unwrap_or(OomAction::Stop);
+            let cgroups = match cgroups::CGroups::new(
+                &self.config.cgroups,
+                &container,
+                &c,
+                oom,
+                self.events_tx.clone(),
+            )
+            .await
+            {
+                Ok(cgroups) => cgroups,
+                Err(e) => return Err(discard(e.into(), Some(process), Some(debug), None).await),
+            };
+
+            if let Err(e) = cgroups.assign(pid).await {
+                return Err(discard(e.into(), Some(process), Some(debug), Some(cgroups)).await);
+            }
             Some(cgroups)
         } else {
             None
@@ -433,11 +1146,7 @@ impl<'a> State<'a> {
             result::Result::Ok(process) => process,
             result::Result::Err(e) => {
                 warn!("Failed to start {}: {}", container, e);
-                debug.destroy().await.expect("Failed to destroy debug");
-                if let Some(cgroups) = cgroups {
-                    cgroups.destroy().await.expect("Failed to destroy cgroups");
-                }
-                return Err(e);
+                return Err(discard(e, None, Some(debug), cgroups).await);
             }
         };
 
@@ -449,8 +1158,12 @@ impl<'a> State<'a> {
             started: time::Instant::now(),
             debug,
             cgroups,
+            paused: false,
         });
 
+        let resources = resources(&mounted_container.manifest);
+        self.acquire_resources(&resources);
+
         info!(
             "Started {} in {:.03}s",
             container,
@@ -463,47 +1176,306 @@ impl<'a> State<'a> {
         Ok(())
     }
 
+    /// Start all containers with `autostart` set in their manifest. The containers are
+    /// started ordered by name and version. A failing start is reported with a notification
+    /// and does not prevent the remaining containers from being started.
+    pub(super) async fn autostart(&mut self) {
+        let mut autostart = self
+            .repositories
+            .values()
+            .flat_map(|repository| repository.containers())
+            .filter(|npk| npk.manifest().autostart.unwrap_or_default())
+            .filter_map(|npk| {
+                let manifest = npk.manifest();
+                let container = Container::new(manifest.name.clone(), manifest.version.clone());
+                if manifest.init.is_none() {
+                    warn!("Skipping autostart of resource container {}", container);
+                    None
+                } else {
+                    Some(container)
+                }
+            })
+            .collect::<Vec<_>>();
+        autostart.sort();
+
+        for container in &autostart {
+            info!("Autostarting {}", container);
+            self.start(container, StartOrigin::Runtime).await;
+        }
+    }
+
     /// Stop a application. Timeout specifies the time until the process is
-    /// SIGKILLed if it doesn't exit when receiving a SIGTERM
+    /// SIGKILLed if it doesn't exit when receiving the stop signal. The signal and
+    /// the timeout if not set are taken from the manifest. Started containers
+    /// that depend on `container` are stopped before.
     pub(super) async fn stop(
         &mut self,
         container: &Container,
-        timeout: time::Duration,
+        timeout: Option<time::Duration>,
+    ) -> Result<(), Error> {
+        if self.is_started(container) {
+            for dependent in self.dependents(container) {
+                info!("Stopping {} which depends on {}", dependent, container);
+                self.stop_container(&dependent, timeout).await?;
+            }
+        }
+        self.stop_container(container, timeout).await
+    }
+
+    /// Stop a single application without considering its dependents
+    async fn stop_container(
+        &mut self,
+        container: &Container,
+        timeout: Option<time::Duration>,
     ) -> Result<(), Error> {
-        if let Some(process) = self
+        let mounted_container = self
             .containers
-            .get_mut(&container)
-            .and_then(|c| c.process.take())
-        {
-            info!("Terminating {}", container);
-            let exit_status = process.terminate(timeout).await.expect("Failed to stop");
+            .get_mut(container)
+            .filter(|c| c.process.is_some())
+            .ok_or_else(|| Error::StopContainerNotStarted(container.clone()))?;
+        let process = mounted_container.process.take().expect("Missing process");
+        let resources = resources(&mounted_container.manifest);
+
+        // Signal and timeout from the manifest unless a timeout is given explicitly
+        let stop = mounted_container.manifest.stop.as_ref();
+        let signal = stop
+            .and_then(|stop| stop.signal.as_deref())
+            .map(|signal| Signal::from_str(signal).expect("Invalid stop signal"))
+            .unwrap_or(Signal::SIGTERM);
+        let timeout = timeout.unwrap_or_else(|| {
+            let timeout = stop.and_then(|stop| stop.timeout);
+            time::Duration::from_millis(timeout.unwrap_or(DEFAULT_STOP_TIMEOUT))
+        });
 
-            // Send notification to main loop
-            self.notification(Notification::Stopped(container.clone()))
-                .await;
+        info!("Terminating {} with {}", container, signal);
+        let exit_status = process
+            .terminate(signal, timeout)
+            .await
+            .expect("Failed to stop");
 
-            info!("Stopped {} with status {:?}", container, exit_status);
+        // Send notification to main loop
+        self.notification(Notification::Stopped(container.clone()))
+            .await;
 
-            Ok(())
-        } else {
-            Err(Error::StopContainerNotStarted(container.clone()))
-        }
-    }
+        info!("Stopped {} with status {:?}", container, exit_status);
+
+        self.release_resources(&resources).await;
+
+        Ok(())
+    }
+
+    /// Count the use of `resources` by a started container and cancel their
+    /// scheduled umount
+    fn acquire_resources(&mut self, resources: &[Container]) {
+        for resource in resources {
+            *self.resource_refs.entry(resource.clone()).or_default() += 1;
+            if let Some(pending) = self.resource_gc.remove(resource) {
+                debug!("Canceling umount of {}", resource);
+                pending.cancel();
+            }
+        }
+    }
+
+    /// Release `resources` used by a stopped container. Resources that are no longer
+    /// used are umounted according to the configured garbage collection policy.
+    async fn release_resources(&mut self, resources: &[Container]) {
+        for resource in resources {
+            match self.resource_refs.get_mut(resource) {
+                Some(refs) if *refs > 1 => {
+                    *refs -= 1;
+                    continue;
+                }
+                Some(_) => {
+                    self.resource_refs.remove(resource);
+                }
+                None => continue,
+            }
+
+            self.collect_resource(resource).await;
+        }
+    }
+
+    /// Umount the unused `resource` according to the configured garbage collection policy
+    async fn collect_resource(&mut self, resource: &Container) {
+        match self.config.resource_gc {
+            None | Some(ResourceGc::Never) => (),
+            Some(ResourceGc::Immediate) => {
+                info!("Umounting unused resource {}", resource);
+                if let Err(e) = self.umount(resource).await {
+                    warn!("Failed to umount unused resource {}: {}", resource, e);
+                }
+            }
+            Some(ResourceGc::Delayed(delay)) => {
+                let delay = time::Duration::from_millis(delay);
+                debug!("Umounting unused resource {} in {:?}", resource, delay);
+                let token = CancellationToken::new();
+                if let Some(pending) = self.resource_gc.insert(resource.clone(), token.clone()) {
+                    pending.cancel();
+                }
+
+                let events_tx = self.events_tx.clone();
+                let resource = resource.clone();
+                task::spawn(async move {
+                    select! {
+                        _ = token.cancelled() => (),
+                        _ = time::sleep(delay) => {
+                            events_tx.send(Event::ResourceGc(resource)).await.ok();
+                        }
+                    }
+                });
+            }
+        }
+    }
+
+    /// Umount the unused resource `container` once its garbage collection delay expired
+    pub(super) async fn on_resource_gc(&mut self, container: &Container) -> Result<(), Error> {
+        // Check if the umount is still pending. It might have been canceled in the meantime.
+        if self.resource_gc.remove(container).is_none()
+            || self.resource_refs.contains_key(container)
+            || !self.containers.contains_key(container)
+        {
+            return Ok(());
+        }
+
+        info!("Umounting unused resource {}", container);
+        if let Err(e) = self.umount(container).await {
+            warn!("Failed to umount unused resource {}: {}", container, e);
+        }
+        Ok(())
+    }
+
+    /// Freeze all processes of a started container
+    pub(super) async fn pause(&mut self, container: &Container) -> Result<(), Error> {
+        let process = self
+            .containers
+            .get_mut(container)
+            .and_then(|c| c.process.as_mut())
+            .ok_or_else(|| Error::PauseContainerNotStarted(container.clone()))?;
+
+        if process.paused {
+            return Err(Error::PauseContainerPaused(container.clone()));
+        }
+        let cgroups = process
+            .cgroups
+            .as_ref()
+            .ok_or_else(|| Error::PauseContainerUnsupported(container.clone()))?;
+
+        info!("Pausing {}", container);
+        match cgroups.freeze().await {
+            Ok(_) => (),
+            Err(cgroups::Error::NoFreezer) => {
+                return Err(Error::PauseContainerUnsupported(container.clone()))
+            }
+            Err(e) => {
+                // Do not leave the container partially frozen
+                cgroups.thaw().await.ok();
+                return Err(e.into());
+            }
+        }
+        process.paused = true;
+
+        self.notification(Notification::Paused(container.clone()))
+            .await;
+        Ok(())
+    }
+
+    /// Thaw a paused container
+    pub(super) async fn resume(&mut self, container: &Container) -> Result<(), Error> {
+        let process = self
+            .containers
+            .get_mut(container)
+            .and_then(|c| c.process.as_mut())
+            .filter(|p| p.paused)
+            .ok_or_else(|| Error::ResumeContainerNotPaused(container.clone()))?;
+
+        info!("Resuming {}", container);
+        if let Some(cgroups) = process.cgroups.as_ref() {
+            cgroups.thaw().await?;
+        }
+        process.paused = false;
+
+        self.notification(Notification::Resumed(container.clone()))
+            .await;
+        Ok(())
+    }
+
+    /// Send `signal` to the processes of a started container
+    pub(super) async fn kill(
+        &mut self,
+        container: &Container,
+        signal: api::model::Signal,
+    ) -> Result<(), Error> {
+        let process = self
+            .containers
+            .get(container)
+            .and_then(|c| c.process.as_ref())
+            .ok_or_else(|| Error::KillContainerNotStarted(container.clone()))?;
+
+        let signal = i32::try_from(signal)
+            .ok()
+            .and_then(|s| Signal::try_from(s).ok())
+            .ok_or_else(|| Error::KillContainerInvalidSignal(container.clone(), signal))?;
+
+        info!("Sending {} to {}", signal, container);
+        process.process.kill(signal).await
+    }
+
+    /// Execute `cmd` in the namespaces of the started `container`. The output and the
+    /// exit status of the command are sent to `output`.
+    pub(super) async fn exec(
+        &self,
+        container: &Container,
+        cmd: &str,
+        args: &[String],
+        env: &HashMap<String, String>,
+        output: mpsc::Sender<ExecOutput>,
+    ) -> Result<(), Error> {
+        let mounted_container = self
+            .containers
+            .get(container)
+            .filter(|c| c.process.is_some())
+            .ok_or_else(|| Error::ExecContainerNotStarted(container.clone()))?;
+        let context = mounted_container.process.as_ref().expect("Missing process");
+
+        // The command would freeze in the cgroup before it is spawned
+        if context.paused {
+            return Err(Error::ExecContainerPaused(container.clone()));
+        }
+
+        let pid = context.process.pid().await;
+        let exec = self
+            .launcher_island
+            .exec(mounted_container, pid, cmd, args, env)
+            .await?;
+
+        if let Some(cgroups) = context.cgroups.as_ref() {
+            if let Err(e) = cgroups.assign(exec.pid()).await {
+                exec.abort().await;
+                return Err(e.into());
+            }
+        }
+
+        info!("Executing {} in {}", cmd, container);
+        exec.start(output).await
+    }
 
     /// Shutdown the runtime: stop running applications and umount npks
     pub(super) async fn shutdown(mut self) -> Result<(), Error> {
-        // Stop started containers
-        let started = self
-            .containers
-            .iter()
-            .filter_map(|(container, mounted_container)| {
-                mounted_container.process.as_ref().map(|_| container)
-            })
-            .cloned()
-            .collect::<Vec<_>>();
-        // Stop started applications
-        for container in &started {
-            self.stop(&container, time::Duration::from_secs(5)).await?;
+        // Cancel pending restarts
+        let restarts = self.restarts.keys().cloned().collect::<Vec<_>>();
+        for container in &restarts {
+            self.cancel_restart(container);
+        }
+
+        // Cancel queued and pending starts
+        self.starts.clear();
+        for wait in self.pending_starts.drain(..).filter_map(|start| start.wait) {
+            wait.cancel();
+        }
+
+        // Stop started applications. Dependents are stopped before their dependencies
+        for container in &self.stop_order() {
+            self.stop_container(container, None).await?;
         }
 
         let containers = self.containers.keys().cloned().collect::<Vec<_>>();
@@ -529,11 +1501,285 @@ impl<'a> State<'a> {
         // Add the npk to the repository
         let container = repository.insert(rx).await?;
 
+        // Refuse containers that introduce a dependency cycle or cannot be started
+        if let Err(e) = self.check_installed(&container) {
+            warn!("Failed to install {}: {}", container, e);
+            if let Some(repository) = self.repositories.get_mut(repository_id) {
+                repository.remove(&container).await?;
+            }
+            return Err(e);
+        }
+
         info!("Successfully installed {}", container);
 
         Ok(())
     }
 
+    /// Check that the installed `container` does not introduce a dependency cycle and
+    /// that its seccomp configuration is valid
+    fn check_installed(&self, container: &Container) -> Result<(), Error> {
+        self.start_order(container, true)?;
+        let (npk, _) = self
+            .npk(container)
+            .ok_or_else(|| Error::InvalidContainer(container.clone()))?;
+        island::check_seccomp(self.config, container, npk.manifest())
+    }
+
+    /// Update `old` to the npk received from `rx`. The response is sent once the new
+    /// version of a started container is started or the update failed. The outcome of
+    /// the health window is reported with update notifications only.
+    async fn update(
+        &mut self,
+        old: &Container,
+        rx: &mut mpsc::Receiver<Bytes>,
+        response_tx: oneshot::Sender<api::model::Response>,
+    ) {
+        match self.begin_update(old, rx).await {
+            Ok(Some(new)) => {
+                let origin = StartOrigin::Update {
+                    old: old.clone(),
+                    response_tx,
+                };
+                self.start(&new, origin).await;
+            }
+            Ok(None) => {
+                response_tx.send(Response::Ok(())).ok();
+            }
+            Err(e) => {
+                warn!("Failed to update {}: {}", old, e);
+                response_tx.send(Response::Err(e.into())).ok();
+            }
+        }
+    }
+
+    /// Respond and watch the started new version `new` of a update of `old` for the health
+    /// window or roll back the update if `new` failed to start
+    async fn update_started(
+        &mut self,
+        old: &Container,
+        new: &Container,
+        result: Result<(), Error>,
+        response_tx: oneshot::Sender<api::model::Response>,
+    ) {
+        if let Err(e) = result {
+            warn!("Failed to start {}: {}", new, e);
+            let error = self.rollback_update(old, new, e).await;
+            warn!("Failed to update {}: {}", old, error);
+            response_tx.send(Response::Err(error.into())).ok();
+            return;
+        }
+
+        self.notification(Notification::Update {
+            from: old.clone(),
+            to: new.clone(),
+            phase: UpdatePhase::Started,
+        })
+        .await;
+        response_tx.send(Response::Ok(())).ok();
+
+        let window = self
+            .config
+            .update_health_window
+            .unwrap_or(DEFAULT_UPDATE_HEALTH_WINDOW);
+        let window = time::Duration::from_millis(window);
+        debug!("Watching {} for {:?}", new, window);
+
+        let token = CancellationToken::new();
+        let events_tx = self.events_tx.clone();
+        let container = new.clone();
+        let cancelled = token.clone();
+        task::spawn(async move {
+            select! {
+                _ = cancelled.cancelled() => (),
+                _ = time::sleep(window) => {
+                    events_tx.send(Event::UpdateHealthy(container)).await.ok();
+                }
+            }
+        });
+
+        let update = PendingUpdate {
+            old: old.clone(),
+            window: token,
+        };
+        self.updates.insert(new.clone(), update);
+    }
+
+    /// Install the new version of `old` into the repository of `old`. A started `old` is
+    /// stopped. Returns the new version if it must be started and pass the health window.
+    /// A `old` that is not started is uninstalled right away.
+    async fn begin_update(
+        &mut self,
+        old: &Container,
+        rx: &mut mpsc::Receiver<Bytes>,
+    ) -> Result<Option<Container>, Error> {
+        let repository_id = self
+            .repositories
+            .iter()
+            .find(|(_, repository)| repository.get(old).is_some())
+            .map(|(id, _)| id.clone())
+            .ok_or_else(|| Error::InvalidContainer(old.clone()))?;
+
+        // Refuse concurrent updates and containers other started containers depend on
+        let started = self.is_started(old);
+        if self.is_updating(old) || (started && !self.dependents(old).is_empty()) {
+            return Err(Error::UpdateContainerBusy(old.clone()));
+        }
+
+        let new = self
+            .repositories
+            .get_mut(&repository_id)
+            .expect("Missing repository")
+            .insert(rx)
+            .await?;
+
+        let error = if new.name() != old.name() {
+            Some(Error::UpdateContainerMismatch(old.clone(), new.clone()))
+        } else {
+            self.check_installed(&new).err()
+        };
+        if let Some(e) = error {
+            if let Some(repository) = self.repositories.get_mut(&repository_id) {
+                repository.remove(&new).await?;
+            }
+            return Err(e);
+        }
+
+        self.notification(Notification::Update {
+            from: old.clone(),
+            to: new.clone(),
+            phase: UpdatePhase::Installed,
+        })
+        .await;
+
+        if !started {
+            info!("Installed {} as update of {}", new, old);
+            self.uninstall_updated(old).await;
+            self.notification(Notification::Update {
+                from: old.clone(),
+                to: new.clone(),
+                phase: UpdatePhase::Completed,
+            })
+            .await;
+            return Ok(None);
+        }
+
+        info!("Updating {} to {}", old, new);
+        self.cancel_restart(old);
+        if let Err(e) = self.stop(old, None).await {
+            if let Some(repository) = self.repositories.get_mut(&repository_id) {
+                repository.remove(&new).await?;
+            }
+            return Err(e);
+        }
+        self.notification(Notification::Update {
+            from: old.clone(),
+            to: new.clone(),
+            phase: UpdatePhase::Stopped,
+        })
+        .await;
+
+        Ok(Some(new))
+    }
+
+    /// Return true if a update of a container with the name of `container` is in progress
+    fn is_updating(&self, container: &Container) -> bool {
+        let name = container.name();
+        let starts = self
+            .starts
+            .iter()
+            .map(|(new, origin)| (new, origin))
+            .chain(
+                self.pending_starts
+                    .iter()
+                    .map(|start| (&start.container, &start.origin)),
+            )
+            .filter_map(|(new, origin)| match origin {
+                StartOrigin::Update { old, .. } => Some((new, old)),
+                _ => None,
+            });
+        self.updates
+            .iter()
+            .map(|(new, update)| (new, &update.old))
+            .chain(starts)
+            .any(|(new, old)| new.name() == name || old.name() == name)
+    }
+
+    /// Remove the new version `new` of a failed update and queue the start of `old`.
+    /// Errors of the rollback are returned along with `error`. The queued start is
+    /// processed by the caller.
+    async fn rollback_update(&mut self, old: &Container, new: &Container, error: Error) -> Error {
+        warn!("Rolling back update of {} to {}", old, new);
+        let mut errors = vec![error];
+
+        self.cancel_restart(new);
+        if self.is_started(new) {
+            if let Err(e) = self.stop_container(new, None).await {
+                errors.push(e);
+            }
+        }
+        if self.containers.contains_key(new) {
+            if let Err(e) = self.umount(new).await {
+                errors.push(e);
+            }
+        }
+        for repository in self.repositories.values_mut() {
+            if let Err(e) = repository.remove(new).await {
+                errors.push(e);
+            }
+        }
+
+        self.starts.push_back((old.clone(), StartOrigin::Runtime));
+
+        self.notification(Notification::Update {
+            from: old.clone(),
+            to: new.clone(),
+            phase: UpdatePhase::RolledBack,
+        })
+        .await;
+
+        Error::multiple(errors)
+    }
+
+    /// Complete the update to `container` once the health window passed and uninstall
+    /// the previous version
+    pub(super) async fn on_update_healthy(&mut self, container: &Container) -> Result<(), Error> {
+        // The update might have been rolled back in the meantime
+        if let Some(update) = self.updates.remove(container) {
+            info!("Updated {} to {}", update.old, container);
+            self.uninstall_updated(&update.old).await;
+            self.notification(Notification::Update {
+                from: update.old.clone(),
+                to: container.clone(),
+                phase: UpdatePhase::Completed,
+            })
+            .await;
+        }
+        Ok(())
+    }
+
+    /// Uninstall the previous version `old` of a completed update. `old` is kept if
+    /// another installed container uses it as resource or dependency because the
+    /// manifests refer to resources and dependencies by version.
+    async fn uninstall_updated(&mut self, old: &Container) {
+        let used = self
+            .repositories
+            .values()
+            .flat_map(|repository| repository.containers())
+            .any(|npk| {
+                let manifest = npk.manifest();
+                resources(manifest).contains(old)
+                    || manifest
+                        .depends_on
+                        .iter()
+                        .any(|d| d.name == *old.name() && d.version == *old.version())
+            });
+        if used {
+            info!("Keeping {} that is used by other containers", old);
+        } else if let Err(e) = self.uninstall(old).await {
+            warn!("Failed to uninstall {}: {}", old, e);
+        }
+    }
+
     /// Remove and umount a specific app
     #[allow(clippy::blocks_in_if_conditions)]
     async fn uninstall(&mut self, container: &Container) -> result::Result<(), Error> {
@@ -552,8 +1798,8 @@ impl<'a> State<'a> {
         Ok(())
     }
 
-    /// Handle the exit of a container. The restarting of containers is a subject
-    /// to be removed and handled externally
+    /// Handle the exit of a container. If the manifest of the container contains a
+    /// restart policy a restart is scheduled if applicable
     pub(super) async fn on_exit(
         &mut self,
         container: &Container,
@@ -568,30 +1814,194 @@ impl<'a> State<'a> {
                     exit_status,
                 );
 
+                let restart = mounted_container.manifest.restart.clone();
+                let resources = resources(&mounted_container.manifest);
+
                 process.destroy().await;
+                self.release_resources(&resources).await;
 
                 self.notification(Notification::Exit {
                     container: container.clone(),
                     status: exit_status.clone(),
                 })
                 .await;
+
+                // Dependencies are a start time relation. Dependents keep running.
+                let dependents = self.dependents(container);
+                if !dependents.is_empty() {
+                    let dependents = dependents
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    warn!(
+                        "Dependency {} of the running containers {} exited",
+                        container, dependents
+                    );
+                }
+
+                // A updated container that exits within the health window is rolled back
+                if let Some(update) = self.updates.remove(container) {
+                    update.window.cancel();
+                    warn!(
+                        "Updated container {} exited within the health window",
+                        container
+                    );
+                    let error = Error::UpdateContainerUnhealthy(container.clone());
+                    let error = self.rollback_update(&update.old, container, error).await;
+                    warn!("Failed to update {}: {}", update.old, error);
+                    self.process_starts().await;
+                    return Ok(());
+                }
+
+                if let Some(restart) = restart {
+                    self.schedule_restart(container, &restart, exit_status)
+                        .await;
+                }
             }
         }
         Ok(())
     }
 
-    /// Handle out of memory conditions for container `name`
-    pub(super) async fn on_oom(&mut self, container: &Container) -> Result<(), Error> {
-        if self
-            .containers
-            .get(container)
-            .and_then(|c| c.process.as_ref())
-            .is_some()
+    /// Apply the restart policy `restart` to a exited container. Restarts are delayed
+    /// with a exponential backoff. If the number of restarts within the configured window
+    /// exceeds the limit the container is considered crash looping and not restarted.
+    async fn schedule_restart(
+        &mut self,
+        container: &Container,
+        restart: &Restart,
+        exit_status: &ExitStatus,
+    ) {
+        let restart_needed = match restart.policy {
+            RestartPolicy::Never => false,
+            RestartPolicy::OnFailure => !matches!(exit_status, ExitStatus::Exit(0)),
+            RestartPolicy::Always => true,
+        };
+
+        if !restart_needed {
+            self.cancel_restart(container);
+            return;
+        }
+
+        let restarts = self.restarts.entry(container.clone()).or_default();
+
+        // Forget about restarts that are older than the window
+        if let Some(window) = restart.window.map(time::Duration::from_millis) {
+            while restarts
+                .history
+                .front()
+                .map(|restart| restart.elapsed() > window)
+                .unwrap_or_default()
+            {
+                restarts.history.pop_front();
+            }
+        }
+
+        let count = restarts.history.len() as u32;
+        if restart
+            .max_retries
+            .map(|max| count >= max)
+            .unwrap_or_default()
         {
-            warn!("Process {} is out of memory. Stopping", container);
-            self.notification(Notification::OutOfMemory(container.clone()))
+            warn!(
+                "Container {} is crash looping. Giving up after {} restarts",
+                container, count
+            );
+            self.cancel_restart(container);
+            self.notification(Notification::CrashLoop(container.clone()))
                 .await;
-            self.stop(container, time::Duration::from_secs(5)).await?;
+            return;
+        }
+
+        let backoff = restart.backoff.unwrap_or(DEFAULT_RESTART_BACKOFF);
+        let max_backoff = restart
+            .max_backoff
+            .unwrap_or(DEFAULT_RESTART_MAX_BACKOFF)
+            .max(backoff);
+        let delay = backoff
+            .saturating_mul(2u64.saturating_pow(count))
+            .min(max_backoff);
+        let delay = time::Duration::from_millis(delay);
+
+        info!(
+            "Restarting {} in {:?} (restart {})",
+            container,
+            delay,
+            count + 1
+        );
+
+        let token = CancellationToken::new();
+        restarts.history.push_back(time::Instant::now());
+        if let Some(pending) = restarts.pending.replace(token.clone()) {
+            pending.cancel();
+        }
+
+        let events_tx = self.events_tx.clone();
+        let container = container.clone();
+        task::spawn(async move {
+            select! {
+                _ = token.cancelled() => (),
+                _ = time::sleep(delay) => {
+                    events_tx.send(Event::Restart(container)).await.ok();
+                }
+            }
+        });
+    }
+
+    /// Cancel a pending restart and forget the restart history of `container`
+    fn cancel_restart(&mut self, container: &Container) {
+        if let Some(pending) = self
+            .restarts
+            .remove(container)
+            .and_then(|restarts| restarts.pending)
+        {
+            debug!("Canceling restart of {}", container);
+            pending.cancel();
+        }
+    }
+
+    /// Restart `container` once its restart delay expired
+    pub(super) async fn on_restart(&mut self, container: &Container) -> Result<(), Error> {
+        // Check if the restart is still pending. It might have been canceled in the meantime.
+        if self
+            .restarts
+            .get_mut(container)
+            .and_then(|restarts| restarts.pending.take())
+            .is_none()
+        {
+            return Ok(());
+        }
+
+        self.start(container, StartOrigin::Runtime).await;
+        Ok(())
+    }
+
+    /// Handle out of memory conditions for container `name`
+    pub(super) async fn on_oom(&mut self, container: &Container) -> Result<(), Error> {
+        let action = match self.containers.get(container) {
+            Some(c) if c.process.is_some() => c.manifest.oom.unwrap_or(OomAction::Stop),
+            _ => return Ok(()),
+        };
+
+        warn!(
+            "Process {} is out of memory. Action: {:?}",
+            container, action
+        );
+        self.notification(Notification::OutOfMemory(container.clone(), action))
+            .await;
+
+        match action {
+            OomAction::Stop => self.stop(container, None).await?,
+            OomAction::Restart => {
+                // Dependents are stopped along with the container and started afterwards
+                let dependents = self.dependents(container);
+                self.stop(container, None).await?;
+                for container in iter::once(container).chain(dependents.iter().rev()) {
+                    self.start(container, StartOrigin::Runtime).await;
+                }
+            }
+            // The container keeps running or the kernel oom killer selected a victim
+            OomAction::Report | OomAction::Kill => (),
         }
         Ok(())
     }
@@ -605,41 +2015,22 @@ impl<'a> State<'a> {
         match request {
             Request::Message(message) => {
                 let payload = &message.payload;
-                if let api::model::Payload::Request(ref request) = payload {
+                if let api::model::Payload::Request(api::model::Request::Start(container)) = payload
+                {
+                    // A explicit start resets the restart history
+                    self.cancel_restart(container);
+                    let origin = StartOrigin::Console(response_tx);
+                    self.start(&container.clone(), origin).await;
+                } else if let api::model::Payload::Request(ref request) = payload {
                     let response = match request {
                         api::model::Request::Containers => {
                             Response::Containers(self.list_containers().await)
                         }
                         api::model::Request::Install(_, _) => unreachable!(),
+                        api::model::Request::Update(_, _) => unreachable!(),
+                        api::model::Request::Exec(_, _, _, _) => unreachable!(),
                         api::model::Request::Mount(containers) => {
-                            // Collect mount futures
-                            let mut mounts = vec![];
-                            for container in containers {
-                                mounts.push(self.mount(container).await?);
-                            }
-
-                            // Mount ;-)
-                            let results = join_all(mounts).await;
-
-                            for result in results {
-                                match result {
-                                    Ok(mounted_container) => {
-                                        // Add mounted container to our internal housekeeping
-                                        info!("Mounted {}", mounted_container.container);
-                                        self.containers.insert(
-                                            mounted_container.container.clone(),
-                                            mounted_container,
-                                        );
-                                    }
-                                    Err(e) => {
-                                        warn!("Failed to mount: {}", e);
-                                        warn!(
-                                            "Not yet implemented: error handling for bulk mounts"
-                                        );
-                                    }
-                                }
-                            }
-                            Response::Mount(vec![])
+                            Response::Mount(self.mount_all(containers).await)
                         }
                         api::model::Request::Repositories => {
                             Response::Repositories(self.list_repositories())
@@ -651,17 +2042,11 @@ impl<'a> State<'a> {
                                 .expect("Internal channel error on main");
                             Response::Ok(())
                         }
-                        api::model::Request::Start(container) => match self.start(&container).await
-                        {
-                            Ok(_) => Response::Ok(()),
-                            Err(e) => {
-                                warn!("Failed to start {}: {}", container, e);
-                                Response::Err(e.into())
-                            }
-                        },
+                        api::model::Request::Start(_) => unreachable!(),
                         api::model::Request::Stop(container, timeout) => {
+                            self.cancel_restart(container);
                             match self
-                                .stop(&container, std::time::Duration::from_secs(*timeout))
+                                .stop(container, timeout.map(std::time::Duration::from_secs))
                                 .await
                             {
                                 Ok(_) => Response::Ok(()),
@@ -671,7 +2056,35 @@ impl<'a> State<'a> {
                                 }
                             }
                         }
+                        api::model::Request::Pause(container) => {
+                            match self.pause(container).await {
+                                Ok(_) => Response::Ok(()),
+                                Err(e) => {
+                                    warn!("Failed to pause {}: {}", container, e);
+                                    Response::Err(e.into())
+                                }
+                            }
+                        }
+                        api::model::Request::Resume(container) => {
+                            match self.resume(container).await {
+                                Ok(_) => Response::Ok(()),
+                                Err(e) => {
+                                    warn!("Failed to resume {}: {}", container, e);
+                                    Response::Err(e.into())
+                                }
+                            }
+                        }
+                        api::model::Request::Kill(container, signal) => {
+                            match self.kill(container, *signal).await {
+                                Ok(_) => Response::Ok(()),
+                                Err(e) => {
+                                    warn!("Failed to kill {}: {}", container, e);
+                                    Response::Err(e.into())
+                                }
+                            }
+                        }
                         api::model::Request::Umount(container) => {
+                            self.cancel_restart(container);
                             match self.umount(&container).await {
                                 Ok(_) => api::model::Response::Ok(()),
                                 Err(e) => {
@@ -681,6 +2094,7 @@ impl<'a> State<'a> {
                             }
                         }
                         api::model::Request::Uninstall(container) => {
+                            self.cancel_restart(container);
                             match self.uninstall(&container).await {
                                 Ok(_) => api::model::Response::Ok(()),
                                 Err(e) => {
@@ -698,6 +2112,20 @@ impl<'a> State<'a> {
                     warn!("Received message is not a request");
                 }
             }
+            Request::Update(container, ref mut rx) => {
+                let container = container.clone();
+                self.update(&container, rx, response_tx).await;
+            }
+            Request::Exec(container, cmd, args, env, output) => {
+                let response = match self.exec(container, cmd, args, env, output.clone()).await {
+                    Ok(_) => Response::Ok(()),
+                    Err(e) => {
+                        warn!("Failed to exec {} in {}: {}", cmd, container, e);
+                        Response::Err(e.into())
+                    }
+                };
+                response_tx.send(response).ok();
+            }
             Request::Install(repository, ref mut rx) => {
                 let payload = match self.install(&repository, rx).await {
                     Ok(_) => api::model::Response::Ok(()),
@@ -727,22 +2155,27 @@ impl<'a> State<'a> {
                         api::model::Process {
                             pid,
                             uptime: f.started.elapsed().as_nanos() as u64,
-                            resources: api::model::Resources {
-                                memory: {
-                                    {
-                                        let page_size = page_size::get();
-                                        procinfo::pid::statm(pid as i32).ok().map(|statm| {
-                                            api::model::Memory {
-                                                size: (statm.size * page_size) as u64,
-                                                resident: (statm.resident * page_size) as u64,
-                                                shared: (statm.share * page_size) as u64,
-                                                text: (statm.text * page_size) as u64,
-                                                data: (statm.data * page_size) as u64,
-                                            }
-                                        })
-                                    }
-                                },
+                            paused: f.paused,
+                            resources: {
+                                let mut resources = f
+                                    .cgroups
+                                    .as_ref()
+                                    .map(|cgroups| cgroups.resources())
+                                    .unwrap_or_default();
+                                let page_size = page_size::get();
+                                resources.memory =
+                                    procinfo::pid::statm(pid as i32).ok().map(|statm| {
+                                        api::model::Memory {
+                                            size: (statm.size * page_size) as u64,
+                                            resident: (statm.resident * page_size) as u64,
+                                            shared: (statm.share * page_size) as u64,
+                                            text: (statm.text * page_size) as u64,
+                                            data: (statm.data * page_size) as u64,
+                                        }
+                                    });
+                                resources
                             },
+                            rlimits: rlimits(pid),
                         }
                     });
                 let mounted = self.containers.contains_key(&container);
@@ -772,3 +2205,83 @@ impl<'a> State<'a> {
         }
     }
 }
+
+/// Resource containers mounted by `manifest`
+fn resources(manifest: &Manifest) -> Vec<Container> {
+    manifest
+        .mounts
+        .values()
+        .filter_map(|m| match m {
+            Mount::Resource(Resource { name, version, .. }) => {
+                Some(Container::new(name.clone(), version.clone()))
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// Release the parts of a container start that failed with `error`. A created but not
+/// started process is killed. Errors of the cleanup are returned along with `error`.
+async fn discard(
+    error: Error,
+    process: Option<Box<dyn Process>>,
+    debug: Option<super::debug::Debug>,
+    cgroups: Option<cgroups::CGroups>,
+) -> Error {
+    let mut errors = vec![error];
+
+    if let Some(process) = process {
+        let timeout = time::Duration::from_millis(DEFAULT_STOP_TIMEOUT);
+        match process.stop(Signal::SIGKILL, timeout).await {
+            Ok((process, _)) => errors.extend(process.destroy().await.err()),
+            Err(e) => errors.push(e),
+        }
+    }
+    if let Some(debug) = debug {
+        errors.extend(debug.destroy().await.err());
+    }
+    if let Some(cgroups) = cgroups {
+        errors.extend(cgroups.destroy().await.err().map(Error::from));
+    }
+
+    Error::multiple(errors)
+}
+
+/// Read the resource limits of `pid` from procfs
+fn rlimits(pid: Pid) -> HashMap<RLimitResource, RLimitValue> {
+    const LIMITS: &[(&str, RLimitResource)] = &[
+        ("Max cpu time", RLimitResource::Cpu),
+        ("Max file size", RLimitResource::Fsize),
+        ("Max data size", RLimitResource::Data),
+        ("Max stack size", RLimitResource::Stack),
+        ("Max core file size", RLimitResource::Core),
+        ("Max resident set", RLimitResource::Rss),
+        ("Max processes", RLimitResource::Nproc),
+        ("Max open files", RLimitResource::Nofile),
+        ("Max locked memory", RLimitResource::Memlock),
+        ("Max address space", RLimitResource::As),
+        ("Max file locks", RLimitResource::Locks),
+        ("Max pending signals", RLimitResource::Sigpending),
+        ("Max msgqueue size", RLimitResource::Msgqueue),
+        ("Max nice priority", RLimitResource::Nice),
+        ("Max realtime priority", RLimitResource::Rtprio),
+        ("Max realtime timeout", RLimitResource::Rttime),
+    ];
+
+    let limits = match std::fs::read_to_string(format!("/proc/{}/limits", pid)) {
+        Ok(limits) => limits,
+        Err(_) => return HashMap::new(),
+    };
+
+    limits
+        .lines()
+        .filter_map(|line| {
+            let (name, resource) = LIMITS.iter().find(|(name, _)| line.starts_with(name))?;
+            let mut values = line[name.len()..].split_whitespace();
+            // "unlimited" does not parse and is represented as None
+            let soft = values.next()?.parse().ok();
+            let hard = values.next()?.parse().ok();
+            Some((*resource, RLimitValue { soft, hard }))
+        })
+        .collect()
+}
@@ -18,6 +18,14 @@ use std::{env, fs, path::Path};
 const CARGO_MANIFEST: &str = "test_container/Cargo.toml";
 const TEST_CONTAINER_MANIFEST: &str = "test_container/manifest.yaml";
 const TEST_RESOURCE_MANIFEST: &str = "test_resource/manifest.yaml";
+/// Manifests of test containers that run the test container binary with a different configuration
+const TEST_CONTAINER_VARIANT_MANIFESTS: &[&str] = &[
+    "test_container/manifest_autostart.yaml",
+    "test_container/manifest_dependent.yaml",
+    "test_container/manifest_restart.yaml",
+    "test_container/manifest_stop.yaml",
+    "test_container/manifest_update.yaml",
+];
 const KEY: &str = "../examples/keys/northstar.key";
 
 fn main() {
@@ -49,6 +57,17 @@ fn main() {
     )
     .expect("Failed to create test container npk");
 
+    // Pack test container variant npks
+    for manifest in TEST_CONTAINER_VARIANT_MANIFESTS {
+        npk::npk::pack(
+            Path::new(manifest),
+            &npk.join("root"),
+            Path::new(&out_dir),
+            Some(Path::new(KEY)),
+        )
+        .expect("Failed to create test container variant npk");
+    }
+
     // Pack test resource npk
     npk::npk::pack(
         Path::new(TEST_RESOURCE_MANIFEST),
@@ -20,6 +20,7 @@ use super::{
 };
 use anyhow::{anyhow, Context, Result};
 use futures::StreamExt;
+use nix::sys::signal::Signal;
 use northstar::{
     api::{client::Client, model::Notification},
     runtime::{
@@ -28,7 +29,12 @@ use northstar::{
         Container,
     },
 };
-use std::{collections::HashMap, convert::TryInto, path::PathBuf, time::Duration};
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use tempfile::TempDir;
 use tokio::{fs, pin, select, time};
 
@@ -81,12 +87,18 @@ impl Northstar {
             config::Repository {
                 dir: test_repositority,
                 key: Some(example_key.clone()),
+                uid_range: None,
+                gid_range: None,
             },
         );
 
         let mut cgroups = HashMap::new();
         cgroups.insert("memory".into(), PathBuf::from(format!("northstar-{}", pid)));
         cgroups.insert("cpu".into(), PathBuf::from(format!("northstar-{}", pid)));
+        cgroups.insert(
+            "freezer".into(),
+            PathBuf::from(format!("northstar-{}", pid)),
+        );
 
         let console = format!(
             "unix://{}/northstar-{}",
@@ -96,9 +108,9 @@ impl Northstar {
         let console_url = url::Url::parse(&console)?;
 
         let config = Config {
-            console: Some(console_url.clone()),
+            console: Some(console_url),
             run_dir,
-            data_dir: data_dir.clone(),
+            data_dir,
             log_dir,
             repositories,
             cgroups,
@@ -116,9 +128,20 @@ impl Northstar {
                 loop_control: PathBuf::from("/dev/loop-control"),
                 loop_dev: "/dev/loop".into(),
             },
+            resource_gc: None,
+            update_health_window: None,
+            seccomp_profiles: HashMap::new(),
             debug: None,
         };
 
+        Self::start_runtime(config, tmpdir).await
+    }
+
+    /// Start the runtime with `config` and connect to it
+    async fn start_runtime(config: Config, tmpdir: TempDir) -> Result<Northstar> {
+        let console_url = config.console.clone().expect("Missing console url");
+        let data_dir = config.data_dir.clone();
+
         // Start the runtime
         let runtime = runtime::Runtime::start(config.clone())
             .await
@@ -155,6 +178,19 @@ impl Northstar {
         self.tmpdir.close().context("Failed to remove tmpdir")
     }
 
+    /// Shut down the runtime and start it again with the same configuration. The
+    /// installed containers and the data directory are kept.
+    pub async fn restart(self) -> Result<Northstar> {
+        self.runtime
+            .shutdown()
+            .await
+            .context("Failed to stop the runtime")?;
+
+        logger::assume("Closed listener", 5u64).await?;
+
+        Self::start_runtime(self.config, self.tmpdir).await
+    }
+
     /// Return the runtimes configuration
     pub fn config(&self) -> &config::Config {
         &self.config
@@ -176,13 +212,40 @@ impl Northstar {
             .stop(
                 container.name(),
                 container.version(),
-                Duration::from_secs(timeout),
+                Some(Duration::from_secs(timeout)),
             )
             .await
             .context("Failed to stop")?;
         Ok(())
     }
 
+    /// Pause a container
+    pub async fn pause(&self, container: &str) -> Result<()> {
+        let container: Container = container.try_into().expect("Invalid container str");
+        self.client
+            .pause(container.name(), container.version())
+            .await
+            .context("Failed to pause")
+    }
+
+    /// Resume a paused container
+    pub async fn resume(&self, container: &str) -> Result<()> {
+        let container: Container = container.try_into().expect("Invalid container str");
+        self.client
+            .resume(container.name(), container.version())
+            .await
+            .context("Failed to resume")
+    }
+
+    /// Send `signal` to a container
+    pub async fn kill(&self, container: &str, signal: Signal) -> Result<()> {
+        let container: Container = container.try_into().expect("Invalid container str");
+        self.client
+            .kill(container.name(), container.version(), signal as u32)
+            .await
+            .context("Failed to kill")
+    }
+
     /// Umount
     pub async fn umount(&self, container: &str) -> Result<()> {
         let container: Container = container.try_into().expect("Invalid container str");
@@ -193,6 +256,14 @@ impl Northstar {
         Ok(())
     }
 
+    /// Install `npk` into the test repository
+    pub async fn install(&self, npk: &Path) -> Result<()> {
+        self.client
+            .install(npk, "test")
+            .await
+            .with_context(|| format!("Failed to install {}", npk.display()))
+    }
+
     pub async fn install_test_container(&self) -> Result<()> {
         self.client
             .install(test_container_npk().await, "test")
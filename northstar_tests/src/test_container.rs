@@ -28,6 +28,45 @@ lazy_static! {
         std::fs::write(&npk, src).expect("Failed to dump npk");
         npk
     };
+    static ref TEST_CONTAINER_AUTOSTART_NPK: PathBuf = {
+        let src = include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/test_container_autostart-0.0.1.npk"
+        ));
+        let npk = TMPDIR.path().join("test-container-autostart.npk");
+        std::fs::write(&npk, src).expect("Failed to dump npk");
+        npk
+    };
+    static ref TEST_CONTAINER_DEPENDENT_NPK: PathBuf = {
+        let src = include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/test_container_dependent-0.0.1.npk"
+        ));
+        let npk = TMPDIR.path().join("test-container-dependent.npk");
+        std::fs::write(&npk, src).expect("Failed to dump npk");
+        npk
+    };
+    static ref TEST_CONTAINER_RESTART_NPK: PathBuf = {
+        let src = include_bytes!(concat!(
+            env!("OUT_DIR"),
+            "/test_container_restart-0.0.1.npk"
+        ));
+        let npk = TMPDIR.path().join("test-container-restart.npk");
+        std::fs::write(&npk, src).expect("Failed to dump npk");
+        npk
+    };
+    static ref TEST_CONTAINER_STOP_NPK: PathBuf = {
+        let src = include_bytes!(concat!(env!("OUT_DIR"), "/test_container_stop-0.0.1.npk"));
+        let npk = TMPDIR.path().join("test-container-stop.npk");
+        std::fs::write(&npk, src).expect("Failed to dump npk");
+        npk
+    };
+    static ref TEST_CONTAINER_UPDATE_NPK: PathBuf = {
+        let src = include_bytes!(concat!(env!("OUT_DIR"), "/test_container-0.0.2.npk"));
+        let npk = TMPDIR.path().join("test-container-update.npk");
+        std::fs::write(&npk, src).expect("Failed to dump npk");
+        npk
+    };
     static ref TEST_RESOURCE_NPK: PathBuf = {
         let src = include_bytes!(concat!(env!("OUT_DIR"), "/test_resource-0.0.1.npk"));
         let npk = TMPDIR.path().join("test-resource.npk");
@@ -37,6 +76,11 @@ lazy_static! {
 }
 
 pub const TEST_CONTAINER: &str = "test_container:0.0.1:test";
+pub const TEST_CONTAINER_AUTOSTART: &str = "test_container_autostart:0.0.1:test";
+pub const TEST_CONTAINER_DEPENDENT: &str = "test_container_dependent:0.0.1:test";
+pub const TEST_CONTAINER_RESTART: &str = "test_container_restart:0.0.1:test";
+pub const TEST_CONTAINER_STOP: &str = "test_container_stop:0.0.1:test";
+pub const TEST_CONTAINER_UPDATE: &str = "test_container:0.0.2:test";
 pub const TEST_RESOURCE: &str = "test_resource:0.0.1:test";
 
 /// Path to the test container npk
@@ -49,6 +93,35 @@ pub fn test_container() -> Container {
     TEST_CONTAINER.try_into().unwrap()
 }
 
+/// Path to the npk of the test container variant with autostart. The variant depends on
+/// the test container.
+pub async fn test_container_autostart_npk() -> &'static Path {
+    &TEST_CONTAINER_AUTOSTART_NPK
+}
+
+/// Path to the npk of the test container variant that depends on the test container
+/// and fails to start because of a missing resource
+pub async fn test_container_dependent_npk() -> &'static Path {
+    &TEST_CONTAINER_DEPENDENT_NPK
+}
+
+/// Path to the npk of the test container variant that crashes on every start and
+/// is restarted twice
+pub async fn test_container_restart_npk() -> &'static Path {
+    &TEST_CONTAINER_RESTART_NPK
+}
+
+/// Path to the npk of the test container variant that ignores its stop signal SIGINT
+/// and has a stop timeout of one second
+pub async fn test_container_stop_npk() -> &'static Path {
+    &TEST_CONTAINER_STOP_NPK
+}
+
+/// Path to the npk of the next version of the test container
+pub async fn test_container_update_npk() -> &'static Path {
+    &TEST_CONTAINER_UPDATE_NPK
+}
+
 // Path to the test resource npk
 pub async fn test_resource_npk() -> &'static Path {
     &TEST_RESOURCE_NPK
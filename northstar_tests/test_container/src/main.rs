@@ -13,13 +13,18 @@
 //   limitations under the License.
 
 use anyhow::{Context, Result};
-use nix::unistd::{self, Gid};
+use nix::{
+    sys::signal::{self, SigHandler, Signal},
+    unistd::{self, Gid},
+};
 use std::{
     env, fs,
     io::{self, Write},
     iter, mem,
     path::{Path, PathBuf},
-    process, thread, time,
+    process,
+    str::FromStr,
+    thread, time,
 };
 use structopt::StructOpt;
 
@@ -33,6 +38,9 @@ enum TestCommands {
     Echo {
         message: Vec<String>,
     },
+    Ignore {
+        signal: String,
+    },
     Inspect,
     LeakMemory,
     Touch {
@@ -48,6 +56,12 @@ enum TestCommands {
 }
 
 fn main() -> Result<()> {
+    // A command passed as arguments is executed on every start
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if !args.is_empty() {
+        execute(&args.join(" "))?;
+    }
+
     let input = Path::new("/data").join("input.txt");
     if input.exists() {
         println!("Reading {}", input.display());
@@ -57,18 +71,7 @@ fn main() -> Result<()> {
         fs::remove_file(&input)?;
 
         for line in commands.lines() {
-            println!("Executing \"{}\"", line);
-            let command = iter::once("test_container").chain(line.split_whitespace());
-            match TestCommands::from_iter(command) {
-                TestCommands::Cat { path } => cat(&path)?,
-                TestCommands::Crash => crash(),
-                TestCommands::Echo { message } => echo(&message),
-                TestCommands::Inspect => inspect(),
-                TestCommands::LeakMemory => leak_memory(),
-                TestCommands::Touch { path } => touch(&path)?,
-                TestCommands::Sleep { seconds } => sleep(seconds),
-                TestCommands::Write { message, path } => write(&message, path.as_path())?,
-            };
+            execute(line)?;
         }
     }
 
@@ -78,6 +81,23 @@ fn main() -> Result<()> {
     Ok(())
 }
 
+fn execute(line: &str) -> Result<()> {
+    println!("Executing \"{}\"", line);
+    let command = iter::once("test_container").chain(line.split_whitespace());
+    match TestCommands::from_iter(command) {
+        TestCommands::Cat { path } => cat(&path)?,
+        TestCommands::Crash => crash(),
+        TestCommands::Echo { message } => echo(&message),
+        TestCommands::Ignore { signal } => ignore(&signal)?,
+        TestCommands::Inspect => inspect(),
+        TestCommands::LeakMemory => leak_memory(),
+        TestCommands::Touch { path } => touch(&path)?,
+        TestCommands::Sleep { seconds } => sleep(seconds),
+        TestCommands::Write { message, path } => write(&message, path.as_path())?,
+    };
+    Ok(())
+}
+
 fn dump(file: &str) {
     println!("{}:", file);
     fs::read_to_string(file)
@@ -104,6 +124,13 @@ fn echo(message: &[String]) {
     println!("{}", message.join(" "));
 }
 
+fn ignore(signal: &str) -> Result<()> {
+    let signal = Signal::from_str(signal).with_context(|| format!("Invalid signal {}", signal))?;
+    unsafe { signal::signal(signal, SigHandler::SigIgn) }
+        .map(drop)
+        .with_context(|| format!("Failed to ignore {}", signal))
+}
+
 fn write(input: &str, path: &Path) -> Result<()> {
     fs::write(path, input)
         .with_context(|| format!("Failed to write \"{}\" to {}", input, path.display()))
@@ -16,17 +16,23 @@ use anyhow::Result;
 use futures::{SinkExt, StreamExt};
 use log::debug;
 use logger::assume;
+use nix::sys::signal::Signal;
 use northstar::api::{
     self,
-    model::{self, ConnectNack, ExitStatus, Notification},
+    model::{self, ConnectNack, Container, ExitStatus, MountResult, Notification, UpdatePhase},
 };
 use northstar_tests::{
     logger,
     runtime::Northstar,
     test,
-    test_container::{TEST_CONTAINER, TEST_RESOURCE},
+    test_container::{
+        test_container_autostart_npk, test_container_dependent_npk, test_container_restart_npk,
+        test_container_stop_npk, test_container_update_npk, TEST_CONTAINER,
+        TEST_CONTAINER_AUTOSTART, TEST_CONTAINER_DEPENDENT, TEST_CONTAINER_RESTART,
+        TEST_CONTAINER_STOP, TEST_CONTAINER_UPDATE, TEST_RESOURCE,
+    },
 };
-use std::path::PathBuf;
+use std::{collections::HashMap, convert::TryInto, path::PathBuf};
 use tokio::{
     io::{AsyncRead, AsyncWrite},
     net::UnixStream,
@@ -125,7 +131,8 @@ test!(mount_umount_test_container_via_client, {
     let containers = containers
         .iter()
         .map(|c| (c.container.name().as_str(), c.container.version()));
-    (*runtime).mount(containers).await?;
+    let results = (*runtime).mount(containers).await?;
+    assert!(results.iter().all(|(_, result)| *result == MountResult::Ok));
 
     // Umount
     let containers = &runtime.containers().await?;
@@ -137,6 +144,44 @@ test!(mount_umount_test_container_via_client, {
     runtime.shutdown().await
 });
 
+// Mount the test container twice in one request. Each requested entry gets a result.
+test!(mount_duplicate_test_container_via_client, {
+    let runtime = Northstar::launch_install_test_container().await?;
+
+    let container: Container = TEST_CONTAINER.try_into().unwrap();
+    let entry = (container.name().as_str(), container.version());
+    let results = (*runtime).mount(vec![entry, entry]).await?;
+    assert_eq!(
+        results,
+        vec![
+            (container.clone(), MountResult::Ok),
+            (container.clone(), MountResult::Ok)
+        ]
+    );
+
+    runtime.umount(TEST_CONTAINER).await?;
+    runtime.shutdown().await
+});
+
+// Install and update with a npk that does not exist fail with an io error
+test!(install_update_missing_npk, {
+    let runtime = Northstar::launch_install_test_container().await?;
+
+    let npk = PathBuf::from("/does/not/exist.npk");
+    let result = api::client::Client::install(&runtime, &npk, "test").await;
+    assert!(matches!(result, Err(api::client::Error::Io(_))));
+
+    let container: Container = TEST_CONTAINER.try_into().unwrap();
+    let result =
+        api::client::Client::update(&runtime, container.name(), container.version(), &npk).await;
+    assert!(matches!(result, Err(api::client::Error::Io(_))));
+
+    // The client is still usable
+    runtime.containers().await?;
+
+    runtime.shutdown().await
+});
+
 // Try to stop a not started container and expect an Err
 test!(try_to_stop_unknown_container, {
     let runtime = Northstar::launch().await?;
@@ -368,6 +413,41 @@ test!(proc_is_mounted_ro, {
     runtime.shutdown().await
 });
 
+// Execute the test container binary in the started test container and check its output
+// and exit status
+test!(exec_in_started_container, {
+    let runtime = Northstar::launch_install_test_container().await?;
+    runtime.start(TEST_CONTAINER).await?;
+    assume("Sleeping...", 5).await?;
+
+    runtime.test_cmds("echo hello from exec\ncrash").await;
+    let container: model::Container = TEST_CONTAINER.try_into().expect("Invalid container str");
+    let mut output = runtime
+        .exec(
+            container.name(),
+            container.version(),
+            "/test_container",
+            &[],
+            HashMap::new(),
+        )
+        .await?;
+
+    let mut stdout = Vec::new();
+    let status = loop {
+        match output.recv().await {
+            Some(model::ExecOutput::Stdout(data)) => stdout.extend(data),
+            Some(model::ExecOutput::Stderr(_)) => (),
+            Some(model::ExecOutput::Exit(status)) => break status,
+            None => anyhow::bail!("Exec output closed"),
+        }
+    };
+    assert!(String::from_utf8_lossy(&stdout).contains("hello from exec"));
+    assert_eq!(status, ExitStatus::Signaled(6));
+
+    runtime.stop(TEST_CONTAINER, 5).await?;
+    runtime.shutdown().await
+});
+
 // Open many connections to the runtime
 test!(open_many_connections_to_the_runtime_and_shutdown, {
     let runtime = Northstar::launch().await?;
@@ -428,6 +508,218 @@ test!(check_api_version_on_connect, {
     runtime.shutdown().await
 });
 
+// Install a container with autostart set and check that it is started when
+// the runtime is started again
+test!(autostart_container, {
+    let runtime = Northstar::launch_install_test_container().await?;
+    runtime
+        .install(test_container_autostart_npk().await)
+        .await?;
+
+    let mut runtime = runtime.restart().await?;
+    let container: Container = TEST_CONTAINER_AUTOSTART.try_into().unwrap();
+    runtime
+        .assume_notification(|n| n == &Notification::Started(container.clone()), 10)
+        .await?;
+
+    runtime.shutdown().await
+});
+
+// Start a container that crashes on every start. The container is restarted
+// until the retry limit of its restart policy is reached.
+test!(restart_crashing_container, {
+    let mut runtime = Northstar::launch().await?;
+    runtime.install(test_container_restart_npk().await).await?;
+    runtime.start(TEST_CONTAINER_RESTART).await?;
+
+    let container: Container = TEST_CONTAINER_RESTART.try_into().unwrap();
+    let mut started = 0;
+    let mut exits = 0;
+    runtime
+        .assume_notification(
+            |n| match n {
+                Notification::Started(c) if c == &container => {
+                    started += 1;
+                    false
+                }
+                Notification::Exit {
+                    container: c,
+                    status: ExitStatus::Signaled(6),
+                } if c == &container => {
+                    exits += 1;
+                    false
+                }
+                Notification::CrashLoop(c) => c == &container,
+                _ => false,
+            },
+            10,
+        )
+        .await?;
+
+    // The initial start and two restarts
+    assert_eq!(started, 3);
+    assert_eq!(exits, 3);
+
+    runtime.shutdown().await
+});
+
+// Pause a started container, check that exec is refused and resume it
+test!(pause_resume_test_container, {
+    let mut runtime = Northstar::launch_install_test_container().await?;
+    runtime.start(TEST_CONTAINER).await?;
+    assume("Sleeping...", 5).await?;
+
+    let container: Container = TEST_CONTAINER.try_into().unwrap();
+    runtime.pause(TEST_CONTAINER).await?;
+    runtime
+        .assume_notification(|n| n == &Notification::Paused(container.clone()), 5)
+        .await?;
+
+    let exec = runtime
+        .exec(
+            container.name(),
+            container.version(),
+            "/test_container",
+            &[],
+            HashMap::new(),
+        )
+        .await;
+    assert!(matches!(
+        exec,
+        Err(api::client::Error::Api(model::Error::ExecContainerPaused(
+            _
+        )))
+    ));
+
+    runtime.resume(TEST_CONTAINER).await?;
+    runtime
+        .assume_notification(|n| n == &Notification::Resumed(container.clone()), 5)
+        .await?;
+
+    runtime.stop(TEST_CONTAINER, 5).await?;
+    runtime.shutdown().await
+});
+
+// Send SIGTERM to a started container and check its exit status
+test!(kill_test_container, {
+    let mut runtime = Northstar::launch_install_test_container().await?;
+    runtime.start(TEST_CONTAINER).await?;
+    assume("Sleeping...", 5).await?;
+
+    let container: Container = TEST_CONTAINER.try_into().unwrap();
+    runtime.kill(TEST_CONTAINER, Signal::SIGTERM).await?;
+    runtime
+        .assume_notification(
+            |n| {
+                n == &Notification::Exit {
+                    container: container.clone(),
+                    status: ExitStatus::Signaled(Signal::SIGTERM as u32),
+                }
+            },
+            5,
+        )
+        .await?;
+
+    runtime.shutdown().await
+});
+
+// Stop a container that ignores its stop signal without a timeout. The container
+// is killed after the stop timeout of its manifest.
+test!(stop_container_ignoring_stop_signal, {
+    let runtime = Northstar::launch().await?;
+    runtime.install(test_container_stop_npk().await).await?;
+    runtime.start(TEST_CONTAINER_STOP).await?;
+    assume("Sleeping...", 5).await?;
+
+    let container: Container = TEST_CONTAINER_STOP.try_into().unwrap();
+    let start = time::Instant::now();
+    api::client::Client::stop(&runtime, container.name(), container.version(), None).await?;
+    assert!(start.elapsed() >= time::Duration::from_secs(1));
+
+    assume("Terminating test_container_stop:0.0.1 with SIGINT", 5).await?;
+    assume("Process \\d+ did not exit within 1s. Sending SIGKILL", 5).await?;
+    assume(
+        "Stopped test_container_stop:0.0.1 with status Signaled\\(SIGKILL\\)",
+        5,
+    )
+    .await?;
+
+    runtime.shutdown().await
+});
+
+// Start a container whose start fails after its dependency is started. The
+// dependency is stopped again and the errors of the start and rollback are returned.
+test!(rollback_failed_start_of_dependency_set, {
+    let mut runtime = Northstar::launch_install_test_container().await?;
+    runtime
+        .install(test_container_dependent_npk().await)
+        .await?;
+
+    let container: Container = TEST_CONTAINER_DEPENDENT.try_into().unwrap();
+    let result = api::client::Client::start(&runtime, container.name(), container.version()).await;
+    assert!(matches!(
+        result,
+        Err(api::client::Error::Api(model::Error::Multiple(_)))
+    ));
+
+    let dependency: Container = TEST_CONTAINER.try_into().unwrap();
+    runtime
+        .assume_notification(|n| n == &Notification::Stopped(dependency.clone()), 5)
+        .await?;
+    assert!(runtime.stop(TEST_CONTAINER, 5).await.is_err());
+
+    runtime.shutdown().await
+});
+
+// Update the started test container. The update request returns once the new
+// version is started and the old version is uninstalled once the update completed.
+test!(update_started_test_container, {
+    let mut runtime = Northstar::launch_install_test_container().await?;
+    runtime.start(TEST_CONTAINER).await?;
+    assume("Sleeping...", 5).await?;
+
+    let old: Container = TEST_CONTAINER.try_into().unwrap();
+    let new: Container = TEST_CONTAINER_UPDATE.try_into().unwrap();
+    let npk = test_container_update_npk().await;
+    api::client::Client::update(&runtime, old.name(), old.version(), npk).await?;
+
+    let completed = Notification::Update {
+        from: old.clone(),
+        to: new.clone(),
+        phase: UpdatePhase::Completed,
+    };
+    runtime.assume_notification(|n| n == &completed, 10).await?;
+
+    let containers = runtime.containers().await?;
+    assert!(containers.iter().all(|c| c.container != old));
+    assert!(containers
+        .iter()
+        .any(|c| c.container == new && c.process.is_some()));
+
+    runtime.stop(TEST_CONTAINER_UPDATE, 5).await?;
+    runtime.shutdown().await
+});
+
+// Update the test container while another container depends on its old version.
+// The old version is kept installed.
+test!(update_test_container_with_dependent, {
+    let runtime = Northstar::launch_install_test_container().await?;
+    runtime
+        .install(test_container_autostart_npk().await)
+        .await?;
+
+    let old: Container = TEST_CONTAINER.try_into().unwrap();
+    let new: Container = TEST_CONTAINER_UPDATE.try_into().unwrap();
+    let npk = test_container_update_npk().await;
+    api::client::Client::update(&runtime, old.name(), old.version(), npk).await?;
+
+    let containers = runtime.containers().await?;
+    assert!(containers.iter().any(|c| c.container == old));
+    assert!(containers.iter().any(|c| c.container == new));
+
+    runtime.shutdown().await
+});
+
 // test!(cgroups_memory, {
 //     let runtime = Northstar::launch().await?;
 
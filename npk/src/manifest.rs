@@ -13,14 +13,14 @@
 //   limitations under the License.
 
 use serde::{
-    de::{Deserializer, Visitor},
+    de::{self, Deserializer, MapAccess, Visitor},
     Deserialize, Serialize,
 };
 use serde_with::skip_serializing_none;
 use std::{
     collections::{HashMap, HashSet},
     fmt, io,
-    path::PathBuf,
+    path::{Component, PathBuf},
     str::FromStr,
 };
 use thiserror::Error;
@@ -28,7 +28,6 @@ use thiserror::Error;
 pub type Name = String;
 pub type Capability = caps::Capability;
 pub type CGroupConfig = HashMap<String, String>;
-pub type CGroups = HashMap<String, CGroupConfig>;
 pub type MountOptions = HashSet<MountOption>;
 pub type Version = semver::Version;
 
@@ -52,10 +51,33 @@ pub struct Manifest {
     pub env: Option<HashMap<String, String>>,
     /// Autostart this container upon northstar startup
     pub autostart: Option<bool>,
+    /// Restart policy applied when the container exits
+    pub restart: Option<Restart>,
+    /// Signal and grace period used to stop the container
+    pub stop: Option<Stop>,
+    /// Containers that must be started before this container. Dependents keep
+    /// running if a dependency exits.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<Dependency>,
     /// CGroup config
     pub cgroups: Option<CGroups>,
+    /// Action taken when the container runs out of memory. Defaults to stop.
+    pub oom: Option<OomAction>,
     /// Seccomp configuration
-    pub seccomp: Option<HashMap<String, String>>,
+    pub seccomp: Option<Seccomp>,
+    /// Network namespace configuration
+    pub network: Option<Network>,
+    /// User namespace configuration
+    pub user_namespace: Option<UserNamespace>,
+    /// Run the container in a new UTS namespace
+    pub uts_namespace: Option<bool>,
+    /// Hostname within the UTS namespace. Defaults to the container name.
+    pub hostname: Option<String>,
+    /// Run the container in a new IPC namespace
+    pub ipc_namespace: Option<bool>,
+    /// Resource limits
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub rlimits: HashMap<RLimitResource, RLimitValue>,
     /// List of bind mounts and resources
     #[serde(
         default,
@@ -101,11 +123,199 @@ impl Manifest {
             ));
         }
 
-        // Check for invalid uid 0
-        if self.uid == 0 {
+        if let Some(cgroups) = self.cgroups.as_ref() {
+            cgroups.verify()?;
+        }
+
+        if self.oom.is_some()
+            && !self
+                .cgroups
+                .as_ref()
+                .map(|cgroups| cgroups.memory.is_some() || cgroups.raw.contains_key("memory"))
+                .unwrap_or_default()
+        {
+            return Err(Error::Invalid(
+                "OOM action requires a memory cgroup".to_string(),
+            ));
+        }
+
+        if let Some(SeccompAction::Errno(errno)) = self
+            .seccomp
+            .as_ref()
+            .and_then(|seccomp| seccomp.default.as_ref())
+        {
+            // The errno is passed in the 12 bit data field of the seccomp return value
+            if !(1..=4095).contains(errno) {
+                return Err(Error::Invalid(format!("Invalid seccomp errno {}", errno)));
+            }
+        }
+
+        if let Some(restart) = self.restart.as_ref() {
+            if self.init.is_none() {
+                return Err(Error::Invalid(
+                    "Restart policy not allowed in resource container".to_string(),
+                ));
+            }
+            if let (Some(backoff), Some(max_backoff)) = (restart.backoff, restart.max_backoff) {
+                if backoff > max_backoff {
+                    return Err(Error::Invalid(format!(
+                        "Restart backoff {} exceeds max_backoff {}",
+                        backoff, max_backoff
+                    )));
+                }
+            }
+        }
+
+        if let Some(stop) = self.stop.as_ref() {
+            if self.init.is_none() {
+                return Err(Error::Invalid(
+                    "Stop configuration not allowed in resource container".to_string(),
+                ));
+            }
+            if let Some(signal) = stop.signal.as_ref() {
+                if !STOP_SIGNALS.contains(&signal.as_str()) {
+                    return Err(Error::Invalid(format!("Invalid stop signal {}", signal)));
+                }
+            }
+        }
+
+        if !self.depends_on.is_empty() && self.init.is_none() {
+            return Err(Error::Invalid(
+                "Dependencies not allowed in resource container".to_string(),
+            ));
+        }
+        let mut dependencies = HashSet::new();
+        for dependency in &self.depends_on {
+            if dependency.name == self.name {
+                return Err(Error::Invalid(format!(
+                    "Container {} cannot depend on itself",
+                    self.name
+                )));
+            }
+            if !dependencies.insert(&dependency.name) {
+                return Err(Error::Invalid(format!(
+                    "Duplicate dependency {}",
+                    dependency.name
+                )));
+            }
+            // The ready file must stay within the data directory of the dependency
+            if let Some(file) = dependency
+                .ready
+                .as_ref()
+                .and_then(|ready| ready.file.as_ref())
+            {
+                let valid = file.components().next().is_some()
+                    && file.components().all(|c| matches!(c, Component::Normal(_)));
+                if !valid {
+                    return Err(Error::Invalid(format!(
+                        "Invalid ready file {} of dependency {}",
+                        file.display(),
+                        dependency.name
+                    )));
+                }
+            }
+        }
+
+        // Check for invalid uid 0. Root within a user namespace is mapped to a unprivileged uid.
+        if self.uid == 0 && self.user_namespace.is_none() {
             return Err(Error::Invalid("Invalid uid 0".to_string()));
         }
 
+        if let Some(user_namespace) = self.user_namespace.as_ref() {
+            if self.init.is_none() {
+                return Err(Error::Invalid(
+                    "User namespace not allowed in resource container".to_string(),
+                ));
+            }
+            for (maps, what) in &[
+                (&user_namespace.uid_map, "uid"),
+                (&user_namespace.gid_map, "gid"),
+            ] {
+                for (n, map) in maps.iter().enumerate() {
+                    let valid = map.count > 0
+                        && map.inside.checked_add(map.count).is_some()
+                        && map.outside.checked_add(map.count).is_some();
+                    if !valid {
+                        return Err(Error::Invalid(format!("Invalid {} map {}", what, map)));
+                    }
+                    if maps[..n].iter().any(|other| {
+                        map.inside < other.inside + other.count
+                            && other.inside < map.inside + map.count
+                    }) {
+                        return Err(Error::Invalid(format!("Overlapping {} map {}", what, map)));
+                    }
+                }
+            }
+            if user_namespace.map_uid(self.uid).is_none() {
+                return Err(Error::Invalid(format!("Unmapped uid {}", self.uid)));
+            }
+            if user_namespace.map_gid(self.gid).is_none() {
+                return Err(Error::Invalid(format!("Unmapped gid {}", self.gid)));
+            }
+        }
+
+        if let Some(network) = self.network.as_ref() {
+            if self.init.is_none() {
+                return Err(Error::Invalid(
+                    "Network configuration not allowed in resource container".to_string(),
+                ));
+            }
+            if let Network::Namespace(name) = network {
+                // A named network namespace is owned by the initial user namespace and
+                // cannot be joined from within a new user namespace
+                if self.user_namespace.is_some() {
+                    return Err(Error::Invalid(
+                        "Named network namespace not allowed with a user namespace".to_string(),
+                    ));
+                }
+                if name.is_empty() || name == "." || name == ".." || name.contains(&['/', '\0'][..])
+                {
+                    return Err(Error::Invalid(format!(
+                        "Invalid network namespace name {}",
+                        name
+                    )));
+                }
+            }
+        }
+
+        if (self.uts_namespace.is_some() || self.ipc_namespace.is_some()) && self.init.is_none() {
+            return Err(Error::Invalid(
+                "Namespaces not allowed in resource container".to_string(),
+            ));
+        }
+        if self.hostname.is_some() && !self.uts_namespace.unwrap_or_default() {
+            return Err(Error::Invalid(
+                "Hostname requires a UTS namespace".to_string(),
+            ));
+        }
+        if self.uts_namespace.unwrap_or_default() {
+            // The hostname defaults to the container name
+            let hostname = self.hostname.as_ref().unwrap_or(&self.name);
+            if hostname.is_empty() || hostname.len() > 64 || hostname.contains('\0') {
+                return Err(Error::Invalid(format!("Invalid hostname {}", hostname)));
+            }
+        }
+
+        if !self.rlimits.is_empty() && self.init.is_none() {
+            return Err(Error::Invalid(
+                "Resource limits not allowed in resource container".to_string(),
+            ));
+        }
+        for (resource, value) in &self.rlimits {
+            // A missing limit is unlimited
+            let valid = match (value.soft, value.hard) {
+                (Some(soft), Some(hard)) => soft <= hard,
+                (None, Some(_)) => false,
+                _ => true,
+            };
+            if !valid {
+                return Err(Error::Invalid(format!(
+                    "Soft limit of {:?} exceeds hard limit",
+                    resource
+                )));
+            }
+        }
+
         // Check for null bytes in suppl groups. Rust Strings allow null bytes in Strings.
         // For passing the group names to getgrnam they need to be C string compliant.
         if let Some(suppl_groups) = self.suppl_groups.as_ref() {
@@ -223,6 +433,669 @@ pub enum Mount {
     Tmpfs(Tmpfs),
 }
 
+/// Restart policy
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum RestartPolicy {
+    /// Never restart the container
+    #[serde(rename = "never")]
+    Never,
+    /// Restart the container if it exits with a non zero exit code or is signaled
+    #[serde(rename = "on-failure")]
+    OnFailure,
+    /// Restart the container whenever it exits
+    #[serde(rename = "always")]
+    Always,
+}
+
+/// CGroup configuration. The typed settings are translated to the parameters of
+/// the cgroup version used by the runtime. Parameters of a controller that are not
+/// typed settings e.g `memory: swappiness: 0` are written verbatim like `raw` ones.
+#[skip_serializing_none]
+#[derive(Clone, Default, Eq, PartialEq, Debug, Serialize)]
+pub struct CGroups {
+    /// Memory controller
+    pub memory: Option<MemoryCGroup>,
+    /// CPU controller
+    pub cpu: Option<CpuCGroup>,
+    /// Pids controller
+    pub pids: Option<PidsCGroup>,
+    /// IO controller. Uses the blkio controller on cgroup v1.
+    pub io: Option<IoCGroup>,
+    /// Cpuset controller
+    pub cpuset: Option<CpusetCGroup>,
+    /// Parameters by controller written verbatim to `<controller>.<param>`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub raw: HashMap<String, CGroupConfig>,
+}
+
+/// Memory controller configuration
+#[skip_serializing_none]
+#[derive(Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MemoryCGroup {
+    /// Memory limit in bytes
+    pub limit: Option<u64>,
+}
+
+/// CPU controller configuration
+#[skip_serializing_none]
+#[derive(Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CpuCGroup {
+    /// Relative CPU weight in the range 2 to 262144. Converted to a weight on cgroup v2.
+    pub shares: Option<u64>,
+    /// CPU time in microseconds the container may use within `period`
+    pub quota: Option<u64>,
+    /// Period in microseconds of `quota`. Defaults to 100000.
+    pub period: Option<u64>,
+}
+
+/// Pids controller configuration
+#[skip_serializing_none]
+#[derive(Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PidsCGroup {
+    /// Maximum number of tasks
+    pub max: Option<u64>,
+}
+
+/// IO controller configuration
+#[skip_serializing_none]
+#[derive(Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IoCGroup {
+    /// Relative IO weight in the range 1 to 10000. Converted to the blkio weight on cgroup v1.
+    pub weight: Option<u16>,
+}
+
+/// Cpuset controller configuration
+#[skip_serializing_none]
+#[derive(Clone, Default, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CpusetCGroup {
+    /// CPUs the container may run on e.g "0-3,6"
+    pub cpus: Option<String>,
+    /// Memory nodes the container may allocate from e.g "0"
+    pub mems: Option<String>,
+}
+
+impl CGroups {
+    /// Add the parameters of `controller`. Typed settings are parsed and all other
+    /// parameters are added to `raw`.
+    fn insert(&mut self, controller: String, mut params: CGroupConfig) -> Result<(), String> {
+        fn take<T: FromStr>(
+            params: &mut CGroupConfig,
+            controller: &str,
+            param: &str,
+        ) -> Result<Option<T>, String> {
+            params
+                .remove(param)
+                .map(|value| {
+                    value
+                        .parse()
+                        .map_err(|_| format!("invalid value {} of {}.{}", value, controller, param))
+                })
+                .transpose()
+        }
+
+        let duplicate = match controller.as_str() {
+            "memory" => self
+                .memory
+                .replace(MemoryCGroup {
+                    limit: take(&mut params, &controller, "limit")?,
+                })
+                .is_some(),
+            "cpu" => self
+                .cpu
+                .replace(CpuCGroup {
+                    shares: take(&mut params, &controller, "shares")?,
+                    quota: take(&mut params, &controller, "quota")?,
+                    period: take(&mut params, &controller, "period")?,
+                })
+                .is_some(),
+            "pids" => self
+                .pids
+                .replace(PidsCGroup {
+                    max: take(&mut params, &controller, "max")?,
+                })
+                .is_some(),
+            "io" => self
+                .io
+                .replace(IoCGroup {
+                    weight: take(&mut params, &controller, "weight")?,
+                })
+                .is_some(),
+            "cpuset" => self
+                .cpuset
+                .replace(CpusetCGroup {
+                    cpus: params.remove("cpus"),
+                    mems: params.remove("mems"),
+                })
+                .is_some(),
+            // Controllers without typed settings are created even without parameters
+            _ => {
+                self.raw.entry(controller.clone()).or_default();
+                false
+            }
+        };
+        if duplicate {
+            return Err(format!("duplicate cgroup controller {}", controller));
+        }
+
+        for (param, value) in params {
+            self.insert_raw(&controller, param, value)?;
+        }
+        Ok(())
+    }
+
+    /// Add a parameter that is written verbatim
+    fn insert_raw(&mut self, controller: &str, param: String, value: String) -> Result<(), String> {
+        let params = self.raw.entry(controller.to_string()).or_default();
+        match params.insert(param.clone(), value) {
+            Some(_) => Err(format!(
+                "duplicate cgroup parameter {}.{}",
+                controller, param
+            )),
+            None => Ok(()),
+        }
+    }
+
+    fn verify(&self) -> Result<(), Error> {
+        if let Some(limit) = self.memory.as_ref().and_then(|m| m.limit) {
+            if limit == 0 {
+                return Err(Error::Invalid("Memory limit must not be 0".to_string()));
+            }
+        }
+
+        if let Some(cpu) = self.cpu.as_ref() {
+            if let Some(shares) = cpu.shares {
+                if !(2..=262_144).contains(&shares) {
+                    return Err(Error::Invalid(format!(
+                        "CPU shares {} out of range 2-262144",
+                        shares
+                    )));
+                }
+            }
+            if let Some(quota) = cpu.quota {
+                if quota < 1000 {
+                    return Err(Error::Invalid(format!(
+                        "CPU quota {} is less than 1000us",
+                        quota
+                    )));
+                }
+            }
+            if let Some(period) = cpu.period {
+                if cpu.quota.is_none() {
+                    return Err(Error::Invalid("CPU period without quota".to_string()));
+                }
+                if !(1000..=1_000_000).contains(&period) {
+                    return Err(Error::Invalid(format!(
+                        "CPU period {} out of range 1000-1000000us",
+                        period
+                    )));
+                }
+            }
+        }
+
+        if let Some(max) = self.pids.as_ref().and_then(|p| p.max) {
+            if max == 0 {
+                return Err(Error::Invalid("Pids max must not be 0".to_string()));
+            }
+        }
+
+        if let Some(weight) = self.io.as_ref().and_then(|io| io.weight) {
+            if !(1..=10_000).contains(&weight) {
+                return Err(Error::Invalid(format!(
+                    "IO weight {} out of range 1-10000",
+                    weight
+                )));
+            }
+        }
+
+        if let Some(cpuset) = self.cpuset.as_ref() {
+            for list in cpuset.cpus.iter().chain(cpuset.mems.iter()) {
+                if !is_id_list(list) {
+                    return Err(Error::Invalid(format!("Invalid cpuset list {}", list)));
+                }
+            }
+        }
+
+        // Raw parameters are file names within the container group
+        let is_name = |s: &str, dot: bool| {
+            !s.is_empty()
+                && !s.starts_with('.')
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '_' || (dot && c == '.'))
+        };
+        for (controller, params) in &self.raw {
+            if !is_name(controller, false) {
+                return Err(Error::Invalid(format!(
+                    "Invalid cgroup controller {}",
+                    controller
+                )));
+            }
+            if let Some(param) = params.keys().find(|p| !is_name(p, true)) {
+                return Err(Error::Invalid(format!(
+                    "Invalid cgroup parameter {}.{}",
+                    controller, param
+                )));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<'de> Deserialize<'de> for CGroups {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CGroupsVisitor;
+
+        impl<'de> Visitor<'de> for CGroupsVisitor {
+            type Value = CGroups;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a map of cgroup controllers to parameters")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<CGroups, A::Error> {
+                let mut cgroups = CGroups::default();
+                let mut raw = HashMap::new();
+                while let Some(controller) = map.next_key::<String>()? {
+                    if controller == "raw" {
+                        raw = map.next_value::<HashMap<String, HashMap<String, CGroupValue>>>()?;
+                    } else {
+                        let params = map.next_value::<HashMap<String, CGroupValue>>()?;
+                        let params = params.into_iter().map(|(p, v)| (p, v.0)).collect();
+                        cgroups
+                            .insert(controller, params)
+                            .map_err(de::Error::custom)?;
+                    }
+                }
+                for (controller, params) in raw {
+                    cgroups.raw.entry(controller.clone()).or_default();
+                    for (param, value) in params {
+                        cgroups
+                            .insert_raw(&controller, param, value.0)
+                            .map_err(de::Error::custom)?;
+                    }
+                }
+                Ok(cgroups)
+            }
+        }
+
+        deserializer.deserialize_map(CGroupsVisitor)
+    }
+}
+
+/// Value of a cgroup parameter. Numbers and booleans are accepted as well.
+struct CGroupValue(String);
+
+impl<'de> Deserialize<'de> for CGroupValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct CGroupValueVisitor;
+
+        impl<'de> Visitor<'de> for CGroupValueVisitor {
+            type Value = CGroupValue;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a cgroup parameter value")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<CGroupValue, E> {
+                Ok(CGroupValue(value.to_string()))
+            }
+
+            fn visit_u64<E: de::Error>(self, value: u64) -> Result<CGroupValue, E> {
+                Ok(CGroupValue(value.to_string()))
+            }
+
+            fn visit_i64<E: de::Error>(self, value: i64) -> Result<CGroupValue, E> {
+                Ok(CGroupValue(value.to_string()))
+            }
+
+            fn visit_f64<E: de::Error>(self, value: f64) -> Result<CGroupValue, E> {
+                Ok(CGroupValue(value.to_string()))
+            }
+
+            fn visit_bool<E: de::Error>(self, value: bool) -> Result<CGroupValue, E> {
+                Ok(CGroupValue(value.to_string()))
+            }
+        }
+
+        deserializer.deserialize_any(CGroupValueVisitor)
+    }
+}
+
+/// Return true if `list` is a comma separated list of ids and id ranges e.g "0-3,6"
+fn is_id_list(list: &str) -> bool {
+    list.split(',').all(|item| {
+        let mut bounds = item.splitn(2, '-').map(|id| id.trim().parse::<u32>());
+        match (bounds.next(), bounds.next()) {
+            (Some(Ok(_)), None) => true,
+            (Some(Ok(first)), Some(Ok(last))) => first <= last,
+            _ => false,
+        }
+    })
+}
+
+/// Action taken when a container runs out of memory. On cgroup v1 the kernel oom
+/// killer is disabled for `Stop` and `Restart` and the container stalls until the
+/// runtime stops it. On cgroup v2 the memory limit is applied as `memory.high` for
+/// all actions but `Kill`: the container is throttled instead of killed when it
+/// exceeds the limit.
+#[derive(Clone, Copy, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum OomAction {
+    /// Stop the container
+    #[serde(rename = "stop")]
+    Stop,
+    /// Stop and start the container
+    #[serde(rename = "restart")]
+    Restart,
+    /// Report the condition only. On cgroup v1 the kernel oom killer selects a
+    /// process of the container. On cgroup v2 the container is throttled.
+    #[serde(rename = "report")]
+    Report,
+    /// Let the kernel oom killer select a process of the container and report
+    /// the condition
+    #[serde(rename = "kill")]
+    Kill,
+}
+
+/// Restart configuration
+#[skip_serializing_none]
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Restart {
+    /// Restart policy
+    pub policy: RestartPolicy,
+    /// Maximum number of restarts within `window`. The runtime gives up on the
+    /// container once this limit is reached. Unlimited if not set.
+    pub max_retries: Option<u32>,
+    /// Delay in milliseconds before the first restart. The delay doubles with
+    /// every restart within `window`.
+    pub backoff: Option<u64>,
+    /// Upper limit of the restart delay in milliseconds
+    pub max_backoff: Option<u64>,
+    /// Time window in milliseconds in which restarts are counted. Restarts older
+    /// than the window are forgotten. Restarts are never forgotten if not set.
+    pub window: Option<u64>,
+}
+
+/// Signals that can be used to stop a container. Signals that are ignored or stop
+/// the process by default are not accepted.
+const STOP_SIGNALS: &[&str] = &[
+    "SIGHUP",
+    "SIGINT",
+    "SIGQUIT",
+    "SIGILL",
+    "SIGTRAP",
+    "SIGABRT",
+    "SIGBUS",
+    "SIGFPE",
+    "SIGKILL",
+    "SIGUSR1",
+    "SIGSEGV",
+    "SIGUSR2",
+    "SIGPIPE",
+    "SIGALRM",
+    "SIGTERM",
+    "SIGSTKFLT",
+    "SIGXCPU",
+    "SIGXFSZ",
+    "SIGVTALRM",
+    "SIGPROF",
+    "SIGIO",
+    "SIGPWR",
+    "SIGSYS",
+];
+
+/// Stop configuration
+#[skip_serializing_none]
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Stop {
+    /// Signal sent to the container to request termination e.g SIGINT. Defaults to SIGTERM.
+    pub signal: Option<String>,
+    /// Time in milliseconds the container has to exit after the signal before it
+    /// is killed. Defaults to 5000.
+    pub timeout: Option<u64>,
+}
+
+/// Dependency to a application container that must be started before the
+/// container declaring the dependency
+#[skip_serializing_none]
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// Name of the dependency
+    pub name: Name,
+    /// Version of the dependency
+    pub version: Version,
+    /// Condition that must be met before the dependent container is started.
+    /// The dependency is considered ready once it is started if not set.
+    pub ready: Option<Ready>,
+}
+
+/// Readiness conditions of a dependency
+#[skip_serializing_none]
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Ready {
+    /// Minimum time in milliseconds the dependency must be running
+    pub delay: Option<u64>,
+    /// File relative to the persistent data directory of the dependency whose
+    /// existence signals readiness
+    pub file: Option<PathBuf>,
+    /// Time in milliseconds to wait for the conditions to be met
+    pub timeout: Option<u64>,
+}
+
+/// Seccomp configuration. The legacy form that lists the allowed syscalls with their
+/// rules directly is accepted and merged into `allow`.
+#[skip_serializing_none]
+#[derive(Clone, Eq, PartialEq, Debug, Serialize)]
+pub struct Seccomp {
+    /// Name of a seccomp profile provided by the runtime whose syscalls are allowed
+    pub profile: Option<String>,
+    /// Action taken on syscalls that are not allowed. Defaults to `kill-thread`.
+    pub default: Option<SeccompAction>,
+    /// Allowed syscalls with their argument rules. Rules replace the rules of
+    /// the profile for the same syscall.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub allow: HashMap<String, String>,
+}
+
+/// Action taken by the kernel on syscalls that are not allowed
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum SeccompAction {
+    /// Kill the process
+    #[serde(rename = "kill-process")]
+    KillProcess,
+    /// Kill the thread that issued the syscall
+    #[serde(rename = "kill-thread")]
+    KillThread,
+    /// Fail the syscall with the given errno
+    #[serde(rename = "errno")]
+    Errno(u16),
+    /// Send SIGSYS to the thread that issued the syscall
+    #[serde(rename = "trap")]
+    Trap,
+    /// Log the syscall and allow it
+    #[serde(rename = "log")]
+    Log,
+}
+
+impl<'de> Deserialize<'de> for Seccomp {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SeccompVisitor;
+
+        impl<'de> Visitor<'de> for SeccompVisitor {
+            type Value = Seccomp;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a seccomp configuration or a map of syscalls to rules")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Seccomp, A::Error> {
+                let mut seccomp = Seccomp {
+                    profile: None,
+                    default: None,
+                    allow: HashMap::new(),
+                };
+                let mut legacy = Vec::new();
+                while let Some(key) = map.next_key::<String>()? {
+                    match key.as_str() {
+                        "profile" => seccomp.profile = map.next_value()?,
+                        "default" => seccomp.default = map.next_value()?,
+                        "allow" => seccomp.allow = map.next_value()?,
+                        _ => legacy.push((key, map.next_value::<String>()?)),
+                    }
+                }
+                for (syscall, rule) in legacy {
+                    if seccomp.allow.insert(syscall.clone(), rule).is_some() {
+                        return Err(de::Error::custom(format!(
+                            "duplicate seccomp rule for {}",
+                            syscall
+                        )));
+                    }
+                }
+                Ok(seccomp)
+            }
+        }
+
+        deserializer.deserialize_map(SeccompVisitor)
+    }
+}
+
+/// Network namespace configuration
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+pub enum Network {
+    /// Share the network namespace of the runtime
+    #[serde(rename = "host")]
+    Host,
+    /// Create a new network namespace with only the loopback interface
+    #[serde(rename = "none")]
+    None,
+    /// Join the named network namespace that is created by the runtime
+    #[serde(rename = "namespace")]
+    Namespace(String),
+}
+
+/// User namespace configuration
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct UserNamespace {
+    /// Mapping of uids inside the user namespace to uids outside
+    pub uid_map: Vec<IdMap>,
+    /// Mapping of gids inside the user namespace to gids outside
+    pub gid_map: Vec<IdMap>,
+}
+
+impl UserNamespace {
+    /// Return the uid outside the user namespace of `uid`
+    pub fn map_uid(&self, uid: u32) -> Option<u32> {
+        self.uid_map.iter().find_map(|map| map.map(uid))
+    }
+
+    /// Return the gid outside the user namespace of `gid`
+    pub fn map_gid(&self, gid: u32) -> Option<u32> {
+        self.gid_map.iter().find_map(|map| map.map(gid))
+    }
+}
+
+/// Mapping of a range of ids inside a user namespace to a range outside
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct IdMap {
+    /// First id of the range inside the namespace
+    pub inside: u32,
+    /// First id of the range outside the namespace
+    pub outside: u32,
+    /// Length of the range
+    pub count: u32,
+}
+
+impl IdMap {
+    /// Return the id outside the namespace of `id` if it is within this mapping
+    pub fn map(&self, id: u32) -> Option<u32> {
+        if id >= self.inside && id - self.inside < self.count {
+            Some(self.outside + (id - self.inside))
+        } else {
+            None
+        }
+    }
+}
+
+impl fmt::Display for IdMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {} {}", self.inside, self.outside, self.count)
+    }
+}
+
+/// Resource limited with a rlimit
+#[derive(Clone, Copy, Eq, PartialEq, Hash, Debug, Serialize, Deserialize)]
+pub enum RLimitResource {
+    /// Maximum size of the virtual memory in bytes
+    #[serde(rename = "as")]
+    As,
+    /// Maximum size of core files in bytes
+    #[serde(rename = "core")]
+    Core,
+    /// CPU time limit in seconds
+    #[serde(rename = "cpu")]
+    Cpu,
+    /// Maximum size of the data segment in bytes
+    #[serde(rename = "data")]
+    Data,
+    /// Maximum size of files created in bytes
+    #[serde(rename = "fsize")]
+    Fsize,
+    /// Maximum number of file locks
+    #[serde(rename = "locks")]
+    Locks,
+    /// Maximum number of bytes locked into memory
+    #[serde(rename = "memlock")]
+    Memlock,
+    /// Maximum number of bytes allocated for POSIX message queues
+    #[serde(rename = "msgqueue")]
+    Msgqueue,
+    /// Ceiling of the nice value
+    #[serde(rename = "nice")]
+    Nice,
+    /// Maximum number of open file descriptors
+    #[serde(rename = "nofile")]
+    Nofile,
+    /// Maximum number of processes of the user
+    #[serde(rename = "nproc")]
+    Nproc,
+    /// Maximum resident set size in bytes
+    #[serde(rename = "rss")]
+    Rss,
+    /// Ceiling of the real-time priority
+    #[serde(rename = "rtprio")]
+    Rtprio,
+    /// CPU time limit in microseconds for real-time scheduled processes
+    #[serde(rename = "rttime")]
+    Rttime,
+    /// Maximum number of queued signals
+    #[serde(rename = "sigpending")]
+    Sigpending,
+    /// Maximum size of the stack in bytes
+    #[serde(rename = "stack")]
+    Stack,
+}
+
+/// Soft and hard limit of a rlimit. A missing value is unlimited.
+#[skip_serializing_none]
+#[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RLimitValue {
+    /// Soft limit
+    pub soft: Option<u64>,
+    /// Hard limit
+    pub hard: Option<u64>,
+}
+
 /// IO configuration for stdin, stdout, stderr
 #[derive(Clone, Eq, PartialEq, Debug, Serialize, Deserialize)]
 #[serde(deny_unknown_fields)]
@@ -426,6 +1299,21 @@ mounts:
     dir: /bin/foo
     options: noexec
 autostart: true
+restart:
+  policy: on-failure
+  max_retries: 3
+  backoff: 100
+  max_backoff: 1000
+  window: 60000
+depends_on:
+  - name: database
+    version: 1.0.0
+  - name: broker
+    version: 0.1.0
+    ready:
+      delay: 100
+      file: ready
+      timeout: 1000
 cgroups:
   memory:
     limit_in_bytes: 30
@@ -434,6 +1322,16 @@ cgroups:
 seccomp:
   fork: 1
   waitpid: 1
+network: none
+uts_namespace: true
+hostname: hello
+ipc_namespace: true
+rlimits:
+  nofile:
+    soft: 1024
+    hard: 4096
+  core:
+    soft: 0
 ";
 
         let manifest = Manifest::from_str(&manifest)?;
@@ -446,6 +1344,35 @@ seccomp:
         assert_eq!(args[1], "two");
 
         assert!(manifest.autostart.unwrap());
+        assert_eq!(
+            manifest.restart,
+            Some(Restart {
+                policy: RestartPolicy::OnFailure,
+                max_retries: Some(3),
+                backoff: Some(100),
+                max_backoff: Some(1000),
+                window: Some(60000),
+            })
+        );
+        assert_eq!(
+            manifest.depends_on,
+            vec![
+                Dependency {
+                    name: "database".to_string(),
+                    version: Version::parse("1.0.0")?,
+                    ready: None,
+                },
+                Dependency {
+                    name: "broker".to_string(),
+                    version: Version::parse("0.1.0")?,
+                    ready: Some(Ready {
+                        delay: Some(100),
+                        file: Some(PathBuf::from("ready")),
+                        timeout: Some(1000),
+                    }),
+                },
+            ]
+        );
         let env = manifest.env.ok_or_else(|| anyhow!("Missing env"))?;
         assert_eq!(
             env.get("LD_LIBRARY_PATH"),
@@ -475,20 +1402,52 @@ seccomp:
         mounts.insert(PathBuf::from("/dev"), Mount::Dev);
         assert_eq!(manifest.mounts, mounts);
 
-        let mut cgroups = HashMap::new();
         let mut mem = HashMap::new();
-        let mut cpu = HashMap::new();
         mem.insert("limit_in_bytes".to_string(), "30".to_string());
-        cpu.insert("shares".to_string(), "100".to_string());
-        cgroups.insert("memory".to_string(), mem);
-        cgroups.insert("cpu".to_string(), cpu);
+        let mut raw = HashMap::new();
+        raw.insert("memory".to_string(), mem);
+        let cgroups = CGroups {
+            memory: Some(MemoryCGroup { limit: None }),
+            cpu: Some(CpuCGroup {
+                shares: Some(100),
+                quota: None,
+                period: None,
+            }),
+            raw,
+            ..Default::default()
+        };
 
         assert_eq!(manifest.cgroups, Some(cgroups));
 
-        let mut seccomp = HashMap::new();
-        seccomp.insert("fork".to_string(), "1".to_string());
-        seccomp.insert("waitpid".to_string(), "1".to_string());
-        assert_eq!(manifest.seccomp, Some(seccomp));
+        let mut allow = HashMap::new();
+        allow.insert("fork".to_string(), "1".to_string());
+        allow.insert("waitpid".to_string(), "1".to_string());
+        assert_eq!(
+            manifest.seccomp,
+            Some(Seccomp {
+                profile: None,
+                default: None,
+                allow
+            })
+        );
+        assert_eq!(manifest.network, Some(Network::None));
+        assert_eq!(manifest.uts_namespace, Some(true));
+        assert_eq!(manifest.hostname, Some("hello".to_string()));
+        assert_eq!(manifest.ipc_namespace, Some(true));
+        assert_eq!(
+            manifest.rlimits.get(&RLimitResource::Nofile),
+            Some(&RLimitValue {
+                soft: Some(1024),
+                hard: Some(4096)
+            })
+        );
+        assert_eq!(
+            manifest.rlimits.get(&RLimitResource::Core),
+            Some(&RLimitValue {
+                soft: Some(0),
+                hard: None
+            })
+        );
 
         assert_eq!(
             manifest.capabilities,
@@ -589,6 +1548,462 @@ mounts:
         Manifest::from_str(manifest).unwrap();
     }
 
+    #[test]
+    fn restart() {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+restart:
+  policy: never
+";
+        let manifest = Manifest::from_str(manifest).unwrap();
+        assert_eq!(manifest.restart.unwrap().policy, RestartPolicy::Never);
+
+        // Invalid policy
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+restart:
+  policy: sometimes
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Backoff exceeds max backoff
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+restart:
+  policy: always
+  backoff: 1000
+  max_backoff: 100
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Restart policy on a resource container
+        let manifest = "name: hello\nversion: 0.0.0\nuid: 1000\ngid: 1001
+restart:
+  policy: always
+";
+        assert!(Manifest::from_str(manifest).is_err());
+    }
+
+    #[test]
+    fn stop() {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+stop:
+  signal: SIGINT
+  timeout: 10000
+";
+        let manifest = Manifest::from_str(manifest).unwrap();
+        assert_eq!(
+            manifest.stop,
+            Some(Stop {
+                signal: Some("SIGINT".to_string()),
+                timeout: Some(10000)
+            })
+        );
+
+        // Invalid signals and signals that do not terminate by default
+        for signal in &["SIGSTOP", "SIGCHLD", "SIGCONT", "SIGTSTP", "SIGWINCH"] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+stop:
+  signal: {}
+",
+                signal
+            );
+            assert!(Manifest::from_str(&manifest).is_err());
+        }
+
+        // Stop configuration on a resource container
+        let manifest = "name: hello\nversion: 0.0.0\nuid: 1000\ngid: 1001
+stop:
+  timeout: 1000
+";
+        assert!(Manifest::from_str(manifest).is_err());
+    }
+
+    #[test]
+    fn user_namespace() {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 0\ngid: 0
+user_namespace:
+  uid_map:
+    - inside: 0
+      outside: 100000
+      count: 1000
+  gid_map:
+    - inside: 0
+      outside: 100000
+      count: 1000
+";
+        let manifest = Manifest::from_str(manifest).unwrap();
+        let user_namespace = manifest.user_namespace.unwrap();
+        assert_eq!(user_namespace.map_uid(0), Some(100000));
+        assert_eq!(user_namespace.map_uid(999), Some(100999));
+        assert_eq!(user_namespace.map_uid(1000), None);
+
+        // Uid not mapped
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 0
+user_namespace:
+  uid_map:
+    - inside: 0
+      outside: 100000
+      count: 1000
+  gid_map:
+    - inside: 0
+      outside: 100000
+      count: 1000
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Overlapping ranges
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 0\ngid: 0
+user_namespace:
+  uid_map:
+    - inside: 0
+      outside: 100000
+      count: 1000
+    - inside: 999
+      outside: 200000
+      count: 1
+  gid_map:
+    - inside: 0
+      outside: 100000
+      count: 1000
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Uid 0 without user namespace
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 0\ngid: 0";
+        assert!(Manifest::from_str(manifest).is_err());
+    }
+
+    #[test]
+    fn hostname() {
+        // Hostname without UTS namespace
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+hostname: hello
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Hostname too long
+        let manifest = format!(
+            "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+uts_namespace: true
+hostname: {}
+",
+            "a".repeat(65)
+        );
+        assert!(Manifest::from_str(&manifest).is_err());
+    }
+
+    #[test]
+    fn rlimits() {
+        // Soft limit exceeds hard limit
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+rlimits:
+  nofile:
+    soft: 4096
+    hard: 1024
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Unlimited soft limit with hard limit
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+rlimits:
+  nofile:
+    hard: 1024
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Unknown resource
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+rlimits:
+  files:
+    soft: 1024
+";
+        assert!(Manifest::from_str(manifest).is_err());
+    }
+
+    #[test]
+    fn cgroups() -> Result<()> {
+        let manifest = |cgroups: &str| {
+            Manifest::from_str(&format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001\ncgroups:\n{}",
+                cgroups
+            ))
+        };
+
+        assert!(manifest("  memory:\n    limit: 1000").is_ok());
+        assert!(manifest("  memory:\n    limit: 0").is_err());
+        assert!(manifest("  memory:\n    limit: max").is_err());
+        assert!(manifest("  cpu:\n    shares: 1024").is_ok());
+        assert!(manifest("  cpu:\n    shares: 1").is_err());
+        assert!(manifest("  cpu:\n    quota: 50000\n    period: 100000").is_ok());
+        assert!(manifest("  cpu:\n    quota: 10").is_err());
+        assert!(manifest("  cpu:\n    period: 100000").is_err());
+        assert!(manifest("  cpu:\n    quota: 50000\n    period: 10").is_err());
+        assert!(manifest("  pids:\n    max: 0").is_err());
+        assert!(manifest("  io:\n    weight: 10000").is_ok());
+        assert!(manifest("  io:\n    weight: 0").is_err());
+        assert!(manifest("  cpuset:\n    cpus: 0-3,6\n    mems: \"0\"").is_ok());
+        assert!(manifest("  cpuset:\n    cpus: 3-0").is_err());
+        assert!(manifest("  cpuset:\n    cpus: all").is_err());
+        assert!(manifest("  raw:\n    memory:\n      swappiness: 0").is_ok());
+        assert!(manifest("  raw:\n    memory:\n      memsw.limit_in_bytes: 1000").is_ok());
+        assert!(manifest("  raw:\n    memory:\n      ../tasks: 1").is_err());
+        assert!(manifest("  raw:\n    ../memory:\n      limit_in_bytes: 1").is_err());
+
+        // Typed settings and parameters written verbatim
+        let cgroups = manifest(
+            "  memory:
+    limit: 30
+    swappiness: 0
+  cpu:
+    shares: 100
+    quota: 50000
+    period: 100000
+  pids:
+    max: 100
+  io:
+    weight: 100
+  cpuset:
+    cpus: 0-3,6
+    mems: \"0\"
+  hugetlb: {}
+  raw:
+    memory:
+      use_hierarchy: 1",
+        )?
+        .cgroups
+        .unwrap();
+        let mut memory = HashMap::new();
+        memory.insert("swappiness".to_string(), "0".to_string());
+        memory.insert("use_hierarchy".to_string(), "1".to_string());
+        let mut raw = HashMap::new();
+        raw.insert("memory".to_string(), memory);
+        raw.insert("hugetlb".to_string(), HashMap::new());
+        let expected = CGroups {
+            memory: Some(MemoryCGroup { limit: Some(30) }),
+            cpu: Some(CpuCGroup {
+                shares: Some(100),
+                quota: Some(50000),
+                period: Some(100000),
+            }),
+            pids: Some(PidsCGroup { max: Some(100) }),
+            io: Some(IoCGroup { weight: Some(100) }),
+            cpuset: Some(CpusetCGroup {
+                cpus: Some("0-3,6".to_string()),
+                mems: Some("0".to_string()),
+            }),
+            raw,
+        };
+        assert_eq!(cgroups, expected);
+        let roundtrip = serde_yaml::from_str::<CGroups>(&serde_yaml::to_string(&cgroups)?)?;
+        assert_eq!(roundtrip, expected);
+
+        // A parameter must not be given in the controller and in raw
+        assert!(
+            manifest("  memory:\n    swappiness: 0\n  raw:\n    memory:\n      swappiness: 0")
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn oom() {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+cgroups:
+  memory:
+    limit_in_bytes: 10000000
+oom: restart
+";
+        let manifest = Manifest::from_str(manifest).unwrap();
+        assert_eq!(manifest.oom, Some(OomAction::Restart));
+
+        // Invalid action
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+cgroups:
+  memory:
+    limit_in_bytes: 10000000
+oom: ignore
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // OOM action without memory cgroup
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+oom: kill
+";
+        assert!(Manifest::from_str(manifest).is_err());
+    }
+
+    #[test]
+    fn seccomp() -> Result<()> {
+        let manifest = |errno: u32| {
+            format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+seccomp:
+  default:
+    errno: {}
+",
+                errno
+            )
+        };
+        assert!(Manifest::from_str(&manifest(1)).is_ok());
+        assert!(Manifest::from_str(&manifest(4095)).is_ok());
+        assert!(Manifest::from_str(&manifest(0)).is_err());
+        assert!(Manifest::from_str(&manifest(4096)).is_err());
+        assert!(Manifest::from_str(&manifest(70000)).is_err());
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+seccomp:
+  profile: default
+  default:
+    errno: 1
+  allow:
+    fork: 1
+    socket: arg0 == AF_UNIX
+";
+        let manifest = Manifest::from_str(manifest)?;
+        let mut allow = HashMap::new();
+        allow.insert("fork".to_string(), "1".to_string());
+        allow.insert("socket".to_string(), "arg0 == AF_UNIX".to_string());
+        let seccomp = Seccomp {
+            profile: Some("default".to_string()),
+            default: Some(SeccompAction::Errno(1)),
+            allow,
+        };
+        assert_eq!(manifest.seccomp, Some(seccomp));
+
+        // Roundtrip of the structured form
+        let deserialized = serde_yaml::from_str::<Manifest>(&serde_yaml::to_string(&manifest)?)?;
+        assert_eq!(manifest, deserialized);
+
+        // Legacy form mixed with the structured form
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+seccomp:
+  profile: minimal
+  fork: 1
+";
+        let seccomp = Manifest::from_str(manifest)?.seccomp.unwrap();
+        assert_eq!(seccomp.profile, Some("minimal".to_string()));
+        assert_eq!(seccomp.allow.get("fork"), Some(&"1".to_string()));
+
+        // Duplicate rule in the legacy and the structured form
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+seccomp:
+  allow:
+    fork: 1
+  fork: 1
+";
+        assert!(Manifest::from_str(manifest).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn network() {
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+network:
+  namespace: backend
+";
+        let manifest = Manifest::from_str(manifest).unwrap();
+        assert_eq!(
+            manifest.network,
+            Some(Network::Namespace("backend".to_string()))
+        );
+
+        // Invalid namespace name
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+network:
+  namespace: ../backend
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Network on a resource container
+        let manifest = "name: hello\nversion: 0.0.0\nuid: 1000\ngid: 1001
+network: host
+";
+        assert!(Manifest::from_str(manifest).is_err());
+        // Named network namespace with a user namespace
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 0\ngid: 0
+user_namespace:
+  uid_map:
+    - inside: 0
+      outside: 100000
+      count: 1000
+  gid_map:
+    - inside: 0
+      outside: 100000
+      count: 1000
+network:
+  namespace: backend
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // A new network namespace is created within the user namespace
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 0\ngid: 0
+user_namespace:
+  uid_map:
+    - inside: 0
+      outside: 100000
+      count: 1000
+  gid_map:
+    - inside: 0
+      outside: 100000
+      count: 1000
+network: none
+";
+        assert!(Manifest::from_str(manifest).is_ok());
+    }
+
+    #[test]
+    fn depends_on() {
+        // Dependency on itself
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+depends_on:
+  - name: hello
+    version: 0.0.0
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Duplicate dependency
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+depends_on:
+  - name: foo
+    version: 0.0.1
+  - name: foo
+    version: 0.0.2
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Dependency of a resource container
+        let manifest = "name: hello\nversion: 0.0.0\nuid: 1000\ngid: 1001
+depends_on:
+  - name: foo
+    version: 0.0.1
+";
+        assert!(Manifest::from_str(manifest).is_err());
+
+        // Ready files outside of the data directory of the dependency
+        for file in &["/ready", "../ready", "foo/../../ready", "."] {
+            let manifest = format!(
+                "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+depends_on:
+  - name: foo
+    version: 0.0.1
+    ready:
+      file: {}
+",
+                file
+            );
+            assert!(Manifest::from_str(&manifest).is_err(), "{}", file);
+        }
+
+        let manifest = "name: hello\nversion: 0.0.0\ninit: /binary\nuid: 1000\ngid: 1001
+depends_on:
+  - name: foo
+    version: 0.0.1
+    ready:
+      file: run/ready
+";
+        assert!(Manifest::from_str(manifest).is_ok());
+    }
+
     #[test]
     fn roundtrip() -> Result<()> {
         let m = "
@@ -624,14 +2039,30 @@ mounts:
     type: tmpfs
     size: 42
 autostart: true
+restart:
+  policy: always
+  backoff: 10
+depends_on:
+  - name: database
+    version: 1.0.0
+    ready:
+      file: ready
 cgroups:
   memory:
     limit_in_bytes: 30
   cpu:
     shares: 100
+oom: report
 seccomp:
   fork: 1
   waitpid: 1
+network:
+  namespace: backend
+uts_namespace: true
+ipc_namespace: false
+rlimits:
+  stack:
+    soft: 8388608
 capabilities:
   - CAP_NET_ADMIN
 io:
@@ -15,15 +15,16 @@
 use anyhow::{anyhow, Context, Error, Result};
 use api::{client::Client, model::Message};
 use futures::{sink::SinkExt, StreamExt};
+use nix::sys::signal;
 use northstar::api::{
     self,
-    model::{Container, Request, Version},
+    model::{Container, ExecOutput, ExitStatus, Payload, Request, Response, Signal, Version},
 };
 use std::{convert::TryFrom, path::PathBuf, process, str::FromStr, time};
 use structopt::{clap, clap::AppSettings, StructOpt};
 use tokio::{
     fs,
-    io::{copy, AsyncBufReadExt, BufReader},
+    io::{self, copy, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader},
 };
 
 mod pretty;
@@ -76,9 +77,46 @@ pub enum Subcommand {
         name: String,
         /// Container version
         version: Version,
-        /// Timeout
-        #[structopt(default_value = "5")]
-        timeout: u64,
+        /// Timeout in seconds. Defaults to the manifest stop timeout
+        timeout: Option<u64>,
+    },
+    /// Pause a container
+    Pause {
+        /// Container name
+        name: String,
+        /// Container version
+        version: Version,
+    },
+    /// Resume a paused container
+    Resume {
+        /// Container name
+        name: String,
+        /// Container version
+        version: Version,
+    },
+    /// Send a signal to a container
+    Kill {
+        /// Container name
+        name: String,
+        /// Container version
+        version: Version,
+        /// Signal name or number e.g SIGHUP, HUP or 1
+        #[structopt(default_value = "SIGTERM", parse(try_from_str = parse_signal))]
+        signal: Signal,
+    },
+    /// Execute a command in a started container
+    Exec {
+        /// Container name
+        name: String,
+        /// Container version
+        version: Version,
+        /// Environment variable e.g FOO=bar
+        #[structopt(short, long, parse(try_from_str = parse_env))]
+        env: Vec<(String, String)>,
+        /// Command
+        cmd: String,
+        /// Command arguments. Use -- to pass arguments starting with -
+        args: Vec<String>,
     },
     /// Install a npk
     Install {
@@ -87,6 +125,15 @@ pub enum Subcommand {
         /// Target repository
         repository: String,
     },
+    /// Update a container to a new version
+    Update {
+        /// Container name
+        name: String,
+        /// Container version
+        version: Version,
+        /// Path to the .npk file of the new version
+        npk: PathBuf,
+    },
     /// Uninstall a container
     Uninstall {
         /// Container name
@@ -138,6 +185,29 @@ fn parse_secs(src: &str) -> Result<time::Duration, anyhow::Error> {
         .map_err(Into::into)
 }
 
+/// Parse a signal number or a signal name with or without the SIG prefix
+fn parse_signal(src: &str) -> Result<Signal, anyhow::Error> {
+    if let Ok(signal) = Signal::from_str(src) {
+        return Ok(signal);
+    }
+    let name = src.to_uppercase();
+    let name = if name.starts_with("SIG") {
+        name
+    } else {
+        format!("SIG{}", name)
+    };
+    signal::Signal::from_str(&name)
+        .map(|signal| signal as Signal)
+        .map_err(|_| anyhow!("Invalid signal {}", src))
+}
+
+/// Parse a environment variable in the form KEY=VALUE
+fn parse_env(src: &str) -> Result<(String, String), anyhow::Error> {
+    src.split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| anyhow!("Invalid environment variable {}", src))
+}
+
 impl TryFrom<Subcommand> for Request {
     type Error = Error;
 
@@ -159,6 +229,29 @@ impl TryFrom<Subcommand> for Request {
                 version,
                 timeout,
             } => Ok(Request::Stop(Container::new(name, version), timeout)),
+            Subcommand::Pause { name, version } => {
+                Ok(Request::Pause(Container::new(name, version)))
+            }
+            Subcommand::Resume { name, version } => {
+                Ok(Request::Resume(Container::new(name, version)))
+            }
+            Subcommand::Kill {
+                name,
+                version,
+                signal,
+            } => Ok(Request::Kill(Container::new(name, version), signal)),
+            Subcommand::Exec {
+                name,
+                version,
+                env,
+                cmd,
+                args,
+            } => Ok(Request::Exec(
+                Container::new(name, version),
+                cmd,
+                args,
+                env.into_iter().collect(),
+            )),
             Subcommand::Install {
                 npk,
                 repository: repo_id,
@@ -166,6 +259,10 @@ impl TryFrom<Subcommand> for Request {
                 let size = npk.metadata().map(|m| m.len())?;
                 Ok(Request::Install(repo_id, size))
             }
+            Subcommand::Update { name, version, npk } => {
+                let size = npk.metadata().map(|m| m.len())?;
+                Ok(Request::Update(Container::new(name, version), size))
+            }
             Subcommand::Uninstall { name, version } => {
                 Ok(Request::Uninstall(Container::new(name, version)))
             }
@@ -227,8 +324,10 @@ async fn main() -> Result<()> {
                 .await
                 .context("Failed to send request")?;
 
-            // Extra file transfer for install hack
-            if let Subcommand::Install { npk, .. } = command {
+            let is_exec = matches!(command, Subcommand::Exec { .. });
+
+            // Extra file transfer for install and update hack
+            if let Subcommand::Install { npk, .. } | Subcommand::Update { npk, .. } = command {
                 copy(
                     &mut fs::File::open(npk).await.context("Failed to open npk")?,
                     &mut framed,
@@ -237,6 +336,11 @@ async fn main() -> Result<()> {
                 .context("Failed to stream npk")?;
             }
 
+            // The output of executed commands is streamed after the response
+            if is_exec {
+                process::exit(exec(&mut framed).await?);
+            }
+
             if opt.json {
                 let response = BufReader::new(framed)
                     .lines()
@@ -254,7 +358,7 @@ async fn main() -> Result<()> {
                     .ok_or_else(|| anyhow!("Failed to receive response"))??
                     .payload
                 {
-                    api::model::Payload::Response(response) => pretty::response(&response),
+                    Payload::Response(response) => pretty::response(&response),
                     _ => unreachable!(),
                 };
                 process::exit(exit);
@@ -264,3 +368,35 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
+
+/// Write the output of an executed command to stdout and stderr and return the
+/// exit code of the command
+async fn exec<T>(framed: &mut api::codec::Framed<T>) -> Result<i32>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut stdout = io::stdout();
+    let mut stderr = io::stderr();
+    while let Some(message) = framed.next().await {
+        match message.context("Failed to receive output")?.payload {
+            Payload::Response(Response::Ok(())) => (),
+            Payload::Response(response) => return Ok(pretty::response(&response)),
+            Payload::Exec(ExecOutput::Stdout(data)) => {
+                stdout.write_all(&data).await?;
+                stdout.flush().await?;
+            }
+            Payload::Exec(ExecOutput::Stderr(data)) => {
+                stderr.write_all(&data).await?;
+                stderr.flush().await?;
+            }
+            Payload::Exec(ExecOutput::Exit(status)) => {
+                return Ok(match status {
+                    ExitStatus::Exit(code) => code,
+                    ExitStatus::Signaled(signal) => 128 + signal as i32,
+                })
+            }
+            _ => (),
+        }
+    }
+    Err(anyhow!("Connection closed"))
+}
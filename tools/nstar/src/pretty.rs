@@ -12,10 +12,12 @@
 //   See the License for the specific language governing permissions and
 //   limitations under the License.
 
+use bytesize::ByteSize;
 use itertools::Itertools;
 use model::ExitStatus;
 use northstar::api::model::{
-    self, Container, ContainerData, MountResult, Notification, RepositoryId, Response,
+    self, Container, ContainerData, MountResult, Notification, OomAction, RepositoryId, Response,
+    UpdatePhase,
 };
 use prettytable::{format, Attr, Cell, Row, Table};
 use std::collections::HashSet;
@@ -23,7 +25,16 @@ use tokio::time;
 
 pub(crate) fn notification(notification: &Notification) {
     match notification {
-        Notification::OutOfMemory(c) => println!("container {} is out of memory", c),
+        Notification::OutOfMemory(c, action) => println!(
+            "container {} is out of memory ({})",
+            c,
+            match action {
+                OomAction::Stop => "stopped",
+                OomAction::Restart => "restarted",
+                OomAction::Report => "reported",
+                OomAction::Kill => "killed by the kernel oom killer",
+            }
+        ),
         Notification::Exit { container, status } => println!(
             "container {} exited with status {}",
             container,
@@ -35,7 +46,21 @@ pub(crate) fn notification(notification: &Notification) {
         Notification::Install(c, v) => println!("installed {}:{}", c, v),
         Notification::Uninstalled(c, v) => println!("uninstalled {}:{}", c, v),
         Notification::Started(c) => println!("started {}", c),
+        Notification::StartFailed(c, e) => println!("failed to start {}: {:?}", c, e),
         Notification::Stopped(c) => println!("stopped {}", c),
+        Notification::Paused(c) => println!("paused {}", c),
+        Notification::Resumed(c) => println!("resumed {}", c),
+        Notification::CrashLoop(c) => println!("container {} is crash looping", c),
+        Notification::Update { from, to, phase } => {
+            let phase = match phase {
+                UpdatePhase::Installed => "installed",
+                UpdatePhase::Stopped => "stopped old version",
+                UpdatePhase::Started => "started new version",
+                UpdatePhase::Completed => "completed",
+                UpdatePhase::RolledBack => "rolled back",
+            };
+            println!("update {} -> {}: {}", from, to, phase)
+        }
         Notification::Shutdown => println!("shutting down"),
     }
 }
@@ -51,6 +76,9 @@ pub(crate) fn containers(containers: &[ContainerData]) {
         Cell::new("Mounted").with_style(Attr::Bold),
         Cell::new("PID").with_style(Attr::Bold),
         Cell::new("Uptime").with_style(Attr::Bold),
+        Cell::new("Memory").with_style(Attr::Bold),
+        Cell::new("CPU").with_style(Attr::Bold),
+        Cell::new("PIDs").with_style(Attr::Bold),
     ]));
     for container in containers
         .iter()
@@ -74,7 +102,13 @@ pub(crate) fn containers(containers: &[ContainerData]) {
                 &container
                     .process
                     .as_ref()
-                    .map(|p| p.pid.to_string())
+                    .map(|p| {
+                        if p.paused {
+                            format!("{} (paused)", p.pid)
+                        } else {
+                            p.pid.to_string()
+                        }
+                    })
                     .unwrap_or_default(),
             )
             .with_style(Attr::ForegroundColor(prettytable::color::GREEN)),
@@ -85,6 +119,38 @@ pub(crate) fn containers(containers: &[ContainerData]) {
                     .map(|p| format!("{:?}", time::Duration::from_nanos(p.uptime)))
                     .unwrap_or_default(),
             ),
+            Cell::new(
+                &container
+                    .process
+                    .as_ref()
+                    .and_then(|p| {
+                        // Prefer the usage of the container cgroup over the init process
+                        p.resources
+                            .container_memory
+                            .as_ref()
+                            .map(|m| m.current)
+                            .or_else(|| p.resources.memory.as_ref().map(|m| m.resident))
+                    })
+                    .map(|bytes| ByteSize::b(bytes).to_string())
+                    .unwrap_or_default(),
+            ),
+            Cell::new(
+                &container
+                    .process
+                    .as_ref()
+                    .and_then(|p| p.resources.cpu.as_ref())
+                    .and_then(|cpu| cpu.usage)
+                    .map(|usage| format!("{:?}", time::Duration::from_micros(usage)))
+                    .unwrap_or_default(),
+            ),
+            Cell::new(
+                &container
+                    .process
+                    .as_ref()
+                    .and_then(|p| p.resources.pids)
+                    .map(|pids| pids.to_string())
+                    .unwrap_or_default(),
+            ),
         ]));
     }
 
@@ -146,40 +212,101 @@ pub fn response(response: &Response) -> i32 {
             0
         }
         Response::Err(e) => {
-            match e {
-                model::Error::Configuration(cause) => eprintln!("invalid configuration: {}", cause),
-                model::Error::InvalidContainer(c) => eprintln!("invalid container {}", c),
-                model::Error::UmountBusy(c) => eprintln!("failed to umount {}: busy", c),
-                model::Error::StartContainerStarted(c) => {
-                    eprintln!("failed to start container {}: already started", c)
-                }
-                model::Error::StartContainerResource(c) => {
-                    eprintln!("failed to start container {}: resource", c)
-                }
-                model::Error::StartContainerMissingResource(c, r) => {
-                    eprintln!("failed to start container {}: missing resource {}", c, r)
-                }
-                model::Error::StartContainerFailed(c, r) => {
-                    eprintln!("failed to start container {}: {}", c, r)
-                }
-                model::Error::StopContainerNotStarted(c) => {
-                    eprintln!("failed to stop container {}: not started", c)
-                }
-                model::Error::InvalidRepository(r) => eprintln!("invalid repository {}", r),
-                model::Error::InstallDuplicate(c) => {
-                    eprintln!("failed to install {}: installed", c)
-                }
-                model::Error::Npk(npk, e) => eprintln!("npk error: {}: {}", npk, e),
-                model::Error::NpkArchive(e) => eprintln!("npk error: {}", e),
-                model::Error::Process(e) => eprintln!("process error: {}", e),
-                model::Error::Console(e) => eprintln!("console error: {}", e),
-                model::Error::Cgroups(e) => eprintln!("cgroups error: {}", e),
-                model::Error::Mount(e) => eprintln!("mount error: {}", e),
-                model::Error::Key(e) => eprintln!("key error: {}", e),
-                model::Error::Io(e) => eprintln!("io error: {}", e),
-                model::Error::Os(e) => eprintln!("os error: {}", e),
-            };
+            error(e);
             1
         }
     }
 }
+
+/// Print a error
+fn error(e: &model::Error) {
+    match e {
+        model::Error::Multiple(errors) => errors.iter().for_each(error),
+        model::Error::Configuration(cause) => eprintln!("invalid configuration: {}", cause),
+        model::Error::InvalidContainer(c) => eprintln!("invalid container {}", c),
+        model::Error::UmountBusy(c) => eprintln!("failed to umount {}: busy", c),
+        model::Error::StartContainerStarted(c) => {
+            eprintln!("failed to start container {}: already started", c)
+        }
+        model::Error::StartContainerResource(c) => {
+            eprintln!("failed to start container {}: resource", c)
+        }
+        model::Error::StartContainerMissingResource(c, r) => {
+            eprintln!("failed to start container {}: missing resource {}", c, r)
+        }
+        model::Error::StartContainerMissingDependency(c, d) => {
+            eprintln!("failed to start container {}: missing dependency {}", c, d)
+        }
+        model::Error::StartContainerFailed(c, r) => {
+            eprintln!("failed to start container {}: {}", c, r)
+        }
+        model::Error::StopContainerNotStarted(c) => {
+            eprintln!("failed to stop container {}: not started", c)
+        }
+        model::Error::PauseContainerNotStarted(c) => {
+            eprintln!("failed to pause container {}: not started", c)
+        }
+        model::Error::PauseContainerPaused(c) => {
+            eprintln!("failed to pause container {}: already paused", c)
+        }
+        model::Error::PauseContainerUnsupported(c) => {
+            eprintln!("failed to pause container {}: no freezer cgroup", c)
+        }
+        model::Error::ResumeContainerNotPaused(c) => {
+            eprintln!("failed to resume container {}: not paused", c)
+        }
+        model::Error::KillContainerNotStarted(c) => {
+            eprintln!("failed to kill container {}: not started", c)
+        }
+        model::Error::KillContainerInvalidSignal(c, s) => {
+            eprintln!("failed to kill container {}: invalid signal {}", c, s)
+        }
+        model::Error::MountContainerMissingResource(c, r) => {
+            eprintln!("failed to mount container {}: missing resource {}", c, r)
+        }
+        model::Error::UpdateContainerMismatch(c, n) => {
+            eprintln!(
+                "failed to update container {}: {} is not a new version",
+                c, n
+            )
+        }
+        model::Error::UpdateContainerBusy(c) => {
+            eprintln!("failed to update container {}: busy", c)
+        }
+        model::Error::UpdateContainerUnhealthy(c) => {
+            eprintln!("failed to update container {}: new version exited", c)
+        }
+        model::Error::ExecContainerNotStarted(c) => {
+            eprintln!("failed to exec in container {}: not started", c)
+        }
+        model::Error::ExecContainerPaused(c) => {
+            eprintln!("failed to exec in container {}: paused", c)
+        }
+        model::Error::ExecContainerInvalidCommand(c, e) => {
+            eprintln!("failed to exec in container {}: invalid command: {}", c, e)
+        }
+        model::Error::InvalidRepository(r) => eprintln!("invalid repository {}", r),
+        model::Error::InstallDuplicate(c) => {
+            eprintln!("failed to install {}: installed", c)
+        }
+        model::Error::InstallInvalidSeccomp(c, e) => {
+            eprintln!(
+                "failed to install {}: invalid seccomp configuration: {}",
+                c, e
+            )
+        }
+        model::Error::DependencyCycle(cs) => eprintln!(
+            "dependency cycle: {}",
+            cs.iter().map(ToString::to_string).join(" -> ")
+        ),
+        model::Error::Npk(npk, e) => eprintln!("npk error: {}: {}", npk, e),
+        model::Error::NpkArchive(e) => eprintln!("npk error: {}", e),
+        model::Error::Process(e) => eprintln!("process error: {}", e),
+        model::Error::Console(e) => eprintln!("console error: {}", e),
+        model::Error::Cgroups(e) => eprintln!("cgroups error: {}", e),
+        model::Error::Mount(e) => eprintln!("mount error: {}", e),
+        model::Error::Key(e) => eprintln!("key error: {}", e),
+        model::Error::Io(e) => eprintln!("io error: {}", e),
+        model::Error::Os(e) => eprintln!("os error: {}", e),
+    }
+}
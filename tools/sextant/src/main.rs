@@ -20,6 +20,7 @@ use structopt::StructOpt;
 
 mod inspect;
 mod pack;
+mod seccomp;
 
 #[derive(Debug, StructOpt)]
 #[structopt(about = "Northstar CLI")]
@@ -72,6 +73,12 @@ enum Opt {
         #[structopt(short, long)]
         out: PathBuf,
     },
+    /// Generate a manifest seccomp section from strace logs
+    Seccomp {
+        /// Strace log files e.g created with the runtime strace debug option
+        #[structopt(required = true)]
+        strace: Vec<PathBuf>,
+    },
 }
 
 fn main() -> Result<()> {
@@ -98,6 +105,7 @@ fn main() -> Result<()> {
         Opt::Unpack { npk, out } => npk::npk::unpack(&npk, &out)?,
         Opt::Inspect { npk, short } => inspect::inspect(&npk, short)?,
         Opt::GenKey { name, out } => npk::npk::gen_key(&name, &out)?,
+        Opt::Seccomp { strace } => seccomp::seccomp(&strace)?,
     }
     Ok(())
 }
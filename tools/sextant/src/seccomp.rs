@@ -0,0 +1,112 @@
+// Copyright (c) 2021 ESRLabs
+//
+//   Licensed under the Apache License, Version 2.0 (the "License");
+//   you may not use this file except in compliance with the License.
+//   You may obtain a copy of the License at
+//
+//      http://www.apache.org/licenses/LICENSE-2.0
+//
+//   Unless required by applicable law or agreed to in writing, software
+//   distributed under the License is distributed on an "AS IS" BASIS,
+//   WITHOUT WARRANTIES OR CONDITIONS OF ANY KIND, either express or implied.
+//   See the License for the specific language governing permissions and
+//   limitations under the License.
+
+use anyhow::{Context, Result};
+use std::{
+    collections::BTreeSet,
+    fs::File,
+    io::{self, BufRead, BufReader, Write},
+    path::PathBuf,
+};
+
+/// Generate a manifest `seccomp` section that allows all syscalls found in the strace logs
+pub fn seccomp(strace: &[PathBuf]) -> Result<()> {
+    let mut syscalls = BTreeSet::new();
+    for path in strace {
+        let file =
+            File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+        for line in BufReader::new(file).lines() {
+            let line = line.with_context(|| format!("Failed to read {}", path.display()))?;
+            if let Some(syscall) = parse_syscall(&line) {
+                syscalls.insert(syscall.to_string());
+            }
+        }
+    }
+
+    let stdout = io::stdout();
+    let mut stdout = stdout.lock();
+    writeln!(stdout, "seccomp:")?;
+    writeln!(stdout, "  allow:")?;
+    for syscall in syscalls {
+        writeln!(stdout, "    {}: 1", syscall)?;
+    }
+    Ok(())
+}
+
+/// Extract the syscall name from a strace output line. Lines may be prefixed with
+/// a pid (`-f`) and timestamps (`-t`, `-tt`, `-ttt` or `-r`). Signals, exits and
+/// resumed syscalls are ignored.
+fn parse_syscall(line: &str) -> Option<&str> {
+    let mut line = line.trim_start();
+
+    // Strip "[pid  1234]" prefix
+    if let Some(rest) = line.strip_prefix("[pid") {
+        line = rest.splitn(2, ']').nth(1)?.trim_start();
+    }
+
+    // Strip numeric prefixes like pids and timestamps
+    loop {
+        let token = line.split_whitespace().next()?;
+        if token
+            .chars()
+            .all(|c| c.is_ascii_digit() || c == '.' || c == ':')
+        {
+            line = line[token.len()..].trim_start();
+        } else {
+            break;
+        }
+    }
+
+    let end = line.find('(')?;
+    let name = &line[..end];
+    let valid = name
+        .chars()
+        .next()
+        .map(|c| c.is_ascii_lowercase() || c == '_')
+        .unwrap_or_default()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if valid {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::parse_syscall;
+
+    #[test]
+    fn parse() {
+        assert_eq!(parse_syscall("read(3, \"\", 832) = 0"), Some("read"));
+        assert_eq!(
+            parse_syscall("[pid  1234] openat(AT_FDCWD, \"/lib\", O_RDONLY) = 3"),
+            Some("openat")
+        );
+        assert_eq!(
+            parse_syscall("1234  12:00:00.123456 write(1, \"hello\", 5) = 5"),
+            Some("write")
+        );
+        assert_eq!(
+            parse_syscall("1234  futex(0x7f, FUTEX_WAIT, 2, NULL <unfinished ...>"),
+            Some("futex")
+        );
+        assert_eq!(parse_syscall("<... futex resumed>) = 0"), None);
+        assert_eq!(parse_syscall("--- SIGCHLD {si_signo=SIGCHLD} ---"), None);
+        assert_eq!(parse_syscall("+++ exited with 0 +++"), None);
+        assert_eq!(parse_syscall(""), None);
+    }
+}
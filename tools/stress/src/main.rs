@@ -120,7 +120,7 @@ async fn main() -> Result<()> {
 
                 if mode != Mode::MountUmount {
                     client
-                        .stop(&app, &version, time::Duration::from_secs(5))
+                        .stop(&app, &version, Some(time::Duration::from_secs(5)))
                         .await
                         .context("Failed to stop container")?;
                 }